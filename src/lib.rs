@@ -1,15 +1,40 @@
 pub mod wfc {
+    #[allow(clippy::module_inception)]
     pub mod wfc {
         pub mod algorithm;
+        pub mod classes;
+        pub mod constraints;
+        pub mod diff;
+        pub mod edit;
+        pub mod feasibility;
+        pub mod generator;
+        pub mod propagation;
+        pub mod pruning;
         pub mod rules;
+        pub mod sampler;
+        pub mod stats;
     }
     pub mod parsing {
+        pub mod class_parse;
+        pub mod code_parse;
+        pub mod graph_export;
         pub mod json_parse;
+        pub mod midi_parse;
         pub mod post_processing;
+        pub mod rules_builder;
+        pub mod template;
         pub mod text_parse;
     }
     pub mod cli {
+        pub mod common;
         pub mod compile;
+        pub mod config;
+        pub mod diff;
+        pub mod edit;
+        pub mod export_graph;
         pub mod generate;
+        pub mod repl;
+        pub mod serve;
+        pub mod stats;
     }
 }
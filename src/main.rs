@@ -1,19 +1,114 @@
-use clap::{command, Arg, Command};
-use wfc::wfc::cli::{compile::compile, generate::generate};
+use clap::builder::RangedU64ValueParser;
+use clap::{command, Arg, ArgAction, ArgGroup, ArgMatches, Command};
+use wfc::wfc::cli::{
+    compile::compile,
+    config::apply_config,
+    diff::diff,
+    edit::edit,
+    export_graph::export_graph,
+    generate::{generate, GenerateOptions},
+    repl::repl,
+    serve::serve,
+    stats::stats,
+};
+use wfc::wfc::wfc::edit::Edit;
+use wfc::wfc::wfc::pruning::PruneOptions;
 
-fn main() {
-    let matches = command!()
+/// Makes the arg a flag which can also be given as --flag=false, so that a flag turned on by a
+/// config can be turned off again
+fn bool_flag(arg: Arg) -> Arg {
+    arg.action(ArgAction::Set)
+        .num_args(0..=1)
+        .require_equals(true)
+        .default_value("false")
+        .default_missing_value("true")
+        .value_parser(clap::value_parser!(bool))
+}
+
+/// Returns the command line interface, without any config applied
+fn build_command() -> Command {
+    command!()
         .propagate_version(true)
         .subcommand_required(true)
+        .arg(
+            Arg::new("config")
+                .long("config")
+                .help("a toml file setting the default options, with a section per subcommand")
+                .global(true),
+        )
+        .arg(
+            Arg::new("profile")
+                .long("profile")
+                .help("the profile of the config file to use, from its [profile.<name>] sections")
+                .requires("config")
+                .global(true),
+        )
         .subcommand(
             Command::new("compile")
                 .about("Compiles a text file into rules json")
                 .arg(
                     Arg::new("input_file")
                         .short('i')
-                        .help("the text file to compile to rules"),
+                        .help("the text file to compile to rules, - reads from stdin (can be given several times)")
+                        .action(ArgAction::Append),
+                )
+                .arg(Arg::new("output_file").short('o').help("the output file"))
+                .arg(
+                    Arg::new("tokenizer")
+                        .long("tokenizer")
+                        .help("how to split the input file into tokens, code being for rust and the languages with its comments and quotes")
+                        .value_parser(["text", "midi", "code", "python"])
+                        .default_value("text"),
+                )
+                .arg(
+                    Arg::new("min_count")
+                        .long("min-count")
+                        .help("drop words appearing fewer times than this")
+                        .value_parser(clap::value_parser!(usize))
+                        .default_value("1"),
+                )
+                .arg(
+                    Arg::new("max_vocab")
+                        .long("max-vocab")
+                        .help("keep only this many of the most frequent words")
+                        .value_parser(clap::value_parser!(usize)),
+                )
+                .arg(
+                    Arg::new("min_transition_count")
+                        .long("min-transition-count")
+                        .help("drop transitions seen fewer times than this")
+                        .value_parser(clap::value_parser!(usize))
+                        .default_value("1"),
+                )
+                .arg(
+                    bool_flag(
+                        Arg::new("unk")
+                            .long("unk")
+                            .help("replace dropped words with <unk> instead of removing them"),
+                    ),
+                )
+                .arg(
+                    Arg::new("classes")
+                        .long("classes")
+                        .help("a file with a word and its class on every line, learning the rules between the classes"),
                 )
-                .arg(Arg::new("output_file").short('o').help("the output file")),
+                .arg(
+                    Arg::new("clusters")
+                        .long("clusters")
+                        .help("group the words into this many classes by how alike their neighbours are, learning the rules between the classes")
+                        .value_parser(RangedU64ValueParser::<usize>::new().range(1..)),
+                )
+                .group(
+                    ArgGroup::new("class_source")
+                        .args(["classes", "clusters"])
+                        .requires("members"),
+                )
+                .arg(
+                    Arg::new("members")
+                        .long("members")
+                        .help("the file to write the words of every class to when compiling with classes or clusters")
+                        .requires("class_source"),
+                ),
         )
         .subcommand(
             Command::new("generate")
@@ -28,18 +123,451 @@ fn main() {
                         .short('t')
                         .help("how many tokens to generate")
                         .value_parser(clap::value_parser!(usize)),
+                )
+                .arg(
+                    Arg::new("constraints")
+                        .short('c')
+                        .help("a json file with banned words, required words, max occurrences and n-gram repetition limits"),
+                )
+                .arg(
+                    Arg::new("tokenizer")
+                        .long("tokenizer")
+                        .help("how to turn the generated tokens back into an output (midi is written to stdout as a .mid file)")
+                        .value_parser(["text", "midi", "code", "python"])
+                        .default_value("text"),
+                )
+                .arg(
+                    Arg::new("seed")
+                        .long("seed")
+                        .help("the seed of the random generator, the same seed generates the same output")
+                        .value_parser(clap::value_parser!(u64)),
+                )
+                .arg(
+                    Arg::new("heuristic")
+                        .long("heuristic")
+                        .help("how the next token to collapse is chosen")
+                        .value_parser(["entropy", "left-to-right", "random"])
+                        .default_value("entropy"),
+                )
+                .arg(
+                    bool_flag(
+                        Arg::new("strict")
+                            .long("strict")
+                            .help("fail if any adjacent words of the result are not allowed by the rules"),
+                    ),
+                )
+                .arg(
+                    Arg::new("output_file")
+                        .short('o')
+                        .help("the output file, instead of stdout")
+                        .conflicts_with("output_dir"),
+                )
+                .arg(
+                    Arg::new("output_dir")
+                        .long("output-dir")
+                        .help("the directory to write the samples to, one file per sample"),
+                )
+                .arg(
+                    Arg::new("count")
+                        .short('n')
+                        .long("count")
+                        .help("how many samples to generate")
+                        .value_parser(clap::value_parser!(usize))
+                        .default_value("1"),
+                )
+                .arg(
+                    Arg::new("name_template")
+                        .long("name-template")
+                        .help("the names of the files in the output directory, {n} is the number of the sample and {ext} the extension of the format")
+                        .default_value("sample_{n}.{ext}"),
+                )
+                .arg(
+                    Arg::new("format")
+                        .long("format")
+                        .help("how to write the samples: the merged output, json with the tokens and the output, or the raw tokens")
+                        .value_parser(["text", "json", "tokens"])
+                        .default_value("text"),
+                )
+                .arg(
+                    Arg::new("continue_from")
+                        .long("continue-from")
+                        .help("a text file to continue, the tokens are generated after it"),
+                )
+                .arg(
+                    Arg::new("template")
+                        .long("template")
+                        .help("a text with blanks to fill instead of generating tokens, ___ is a word and ___{min,max} several")
+                        .conflicts_with_all(["tokens", "continue_from"]),
+                )
+                .arg(
+                    Arg::new("jobs")
+                        .short('j')
+                        .long("jobs")
+                        .help("how many threads generate the samples, all the cores by default")
+                        .value_parser(clap::value_parser!(usize)),
+                )
+                .arg(
+                    Arg::new("members")
+                        .long("members")
+                        .help("the words of every class written by compile --classes, replacing the generated classes with their words"),
+                )
+                .arg(
+                    Arg::new("sampler")
+                        .long("sampler")
+                        .help("how the words are chosen: collapsing one token at a time, or drawing from every text which fits the rules, all equally likely or as likely as their transitions (with --members, the sequences of classes are drawn this way)")
+                        .value_parser(["wfc", "uniform", "weighted"])
+                        .default_value("wfc"),
+                )
+                .arg(
+                    bool_flag(
+                        Arg::new("count_texts")
+                            .long("count-texts")
+                            .help("print how many texts of the number of tokens fit the rules instead of generating any"),
+                    )
+                    .conflicts_with("template"),
+                ),
+        )
+        .subcommand(
+            Command::new("repl")
+                .about("Starts an interactive prompt to explore generation with rules json")
+                .arg(
+                    Arg::new("rules")
+                        .short('r')
+                        .help("the rules for the algorithm"),
+                )
+                .arg(
+                    Arg::new("tokens")
+                        .short('t')
+                        .help("how many tokens to generate until changed")
+                        .value_parser(clap::value_parser!(usize))
+                        .default_value("20"),
+                )
+                .arg(
+                    Arg::new("seed")
+                        .long("seed")
+                        .help("the seed of the random generator")
+                        .value_parser(clap::value_parser!(u64)),
                 ),
         )
-        .get_matches();
+        .subcommand(
+            Command::new("serve")
+                .about("Serves generation with rules json over a local http json api")
+                .arg(
+                    Arg::new("rules")
+                        .short('r')
+                        .long("rules")
+                        .help("the rules for the algorithm, as name=file to serve several rules by name")
+                        .action(ArgAction::Append),
+                )
+                .arg(
+                    Arg::new("host")
+                        .long("host")
+                        .help("the address to listen on")
+                        .default_value("127.0.0.1"),
+                )
+                .arg(
+                    Arg::new("port")
+                        .short('p')
+                        .long("port")
+                        .help("the port to listen on")
+                        .value_parser(clap::value_parser!(u16))
+                        .default_value("8080"),
+                ),
+        )
+        .subcommand(
+            Command::new("stats")
+                .about("Prints statistics about a rules json")
+                .arg(
+                    Arg::new("rules")
+                        .short('r')
+                        .help("the rules to inspect"),
+                )
+                .arg(
+                    Arg::new("top")
+                        .long("top")
+                        .help("how many of the most connected words to list")
+                        .value_parser(clap::value_parser!(usize))
+                        .default_value("10"),
+                )
+                .arg(
+                    bool_flag(
+                        Arg::new("json")
+                            .long("json")
+                            .help("print the statistics as json"),
+                    ),
+                ),
+        )
+        .subcommand(
+            Command::new("export-graph")
+                .about("Exports a rules json as a transition graph")
+                .arg(
+                    Arg::new("rules")
+                        .short('r')
+                        .help("the rules to export"),
+                )
+                .arg(Arg::new("output_file").short('o').help("the output file (stdout by default)"))
+                .arg(
+                    Arg::new("format")
+                        .long("format")
+                        .help("the format of the graph")
+                        .value_parser(["dot", "graphml", "json"])
+                        .default_value("dot"),
+                )
+                .arg(
+                    Arg::new("word")
+                        .long("word")
+                        .help("only export the words around this word"),
+                )
+                .arg(
+                    Arg::new("hops")
+                        .long("hops")
+                        .help("how many transitions away from the word to export")
+                        .value_parser(clap::value_parser!(usize))
+                        .default_value("1"),
+                )
+                .arg(
+                    bool_flag(
+                        Arg::new("counts")
+                            .long("counts")
+                            .help("label the edges with how many times they were seen, when the rules have counts"),
+                    ),
+                ),
+        )
+        .subcommand(
+            Command::new("edit")
+                .about("Edits a rules json, keeping the words before and after every word consistent")
+                .subcommand_required(true)
+                .arg(
+                    Arg::new("rules")
+                        .short('r')
+                        .help("the rules to edit")
+                        .global(true),
+                )
+                .arg(
+                    Arg::new("output_file")
+                        .short('o')
+                        .help("the file to write the edited rules to, instead of the rules file")
+                        .global(true),
+                )
+                .subcommand(
+                    Command::new("add-transition")
+                        .about("Allows a word after another, or every word of a path after the previous one")
+                        .arg(
+                            Arg::new("words")
+                                .required(true)
+                                .num_args(2..)
+                                .help("the words, each allowed after the previous one"),
+                        )
+                        .arg(
+                            Arg::new("count")
+                                .long("count")
+                                .help("how many times the transition was seen, once by default for weighted rules")
+                                .value_parser(clap::value_parser!(usize)),
+                        )
+                        .arg(
+                            bool_flag(
+                                Arg::new("create")
+                                    .long("create")
+                                    .help("add the words which aren't in the rules yet"),
+                            ),
+                        ),
+                )
+                .subcommand(
+                    Command::new("remove-transition")
+                        .about("Forbids a word after another")
+                        .arg(Arg::new("before").required(true))
+                        .arg(Arg::new("after").required(true)),
+                )
+                .subcommand(
+                    Command::new("remove-token")
+                        .about("Removes a word and every transition from or to it")
+                        .arg(Arg::new("word").required(true)),
+                )
+                .subcommand(
+                    Command::new("rename-token")
+                        .about("Renames a word")
+                        .arg(Arg::new("from").required(true))
+                        .arg(Arg::new("to").required(true)),
+                )
+                .subcommand(
+                    Command::new("merge-tokens")
+                        .about("Replaces words with a single word allowing every transition of them")
+                        .arg(
+                            Arg::new("words")
+                                .required(true)
+                                .num_args(2..)
+                                .help("the words to merge"),
+                        )
+                        .arg(
+                            Arg::new("into")
+                                .long("into")
+                                .required(true)
+                                .help("the merged word, one of the words or a new one"),
+                        ),
+                ),
+        )
+        .subcommand(
+            Command::new("diff")
+                .about("Prints what changed between two rules json")
+                .arg(Arg::new("old").help("the old rules"))
+                .arg(Arg::new("new").help("the new rules"))
+                .arg(
+                    Arg::new("top")
+                        .long("top")
+                        .help("how many of the biggest probability shifts to list, for weighted rules")
+                        .value_parser(clap::value_parser!(usize))
+                        .default_value("10"),
+                )
+                .arg(
+                    bool_flag(
+                        Arg::new("json")
+                            .long("json")
+                            .help("print the differences as json"),
+                    ),
+                ),
+        )
+}
+
+/// Returns the edits asked for by the arguments of the edit subcommand
+fn get_edits(edit_args: &ArgMatches) -> Vec<Edit> {
+    let get = |args: &ArgMatches, id: &str| args.get_one::<String>(id).cloned().unwrap_or_default();
+
+    let edit = match edit_args.subcommand() {
+        Some(("add-transition", args)) => {
+            let words: Vec<&String> = args
+                .get_many::<String>("words")
+                .map(|words| words.collect())
+                .unwrap_or_default();
+            return words
+                .windows(2)
+                .map(|pair| Edit::AddTransition {
+                    before: pair[0].clone(),
+                    after: pair[1].clone(),
+                    count: args.get_one::<usize>("count").copied(),
+                    create: args.get_flag("create"),
+                })
+                .collect();
+        }
+        Some(("remove-transition", args)) => Edit::RemoveTransition {
+            before: get(args, "before"),
+            after: get(args, "after"),
+        },
+        Some(("remove-token", args)) => Edit::RemoveToken(get(args, "word")),
+        Some(("rename-token", args)) => Edit::RenameToken {
+            from: get(args, "from"),
+            to: get(args, "to"),
+        },
+        Some(("merge-tokens", args)) => Edit::MergeTokens {
+            words: args
+                .get_many::<String>("words")
+                .map(|words| words.cloned().collect())
+                .unwrap_or_default(),
+            into: get(args, "into"),
+        },
+        _ => unreachable!(),
+    };
+    vec![edit]
+}
+
+fn main() {
+    let matches = build_command().get_matches();
+    let matches = match matches.get_one::<String>("config") {
+        Some(config_file_name) => match apply_config(
+            build_command(),
+            config_file_name,
+            matches.get_one::<String>("profile"),
+        ) {
+            Ok(command) => command.get_matches(),
+            Err(e) => {
+                eprintln!("{}", e);
+                return;
+            }
+        },
+        None => matches,
+    };
 
     let result = match matches.subcommand() {
         Some(("compile", compile_args)) => compile(
-            compile_args.get_one::<String>("input_file"),
+            compile_args
+                .get_many::<String>("input_file")
+                .map(|input_files| input_files.collect())
+                .unwrap_or_default(),
             compile_args.get_one::<String>("output_file"),
+            compile_args.get_one::<String>("tokenizer"),
+            &PruneOptions {
+                min_count: compile_args
+                    .get_one::<usize>("min_count")
+                    .copied()
+                    .unwrap_or(1),
+                max_vocab: compile_args.get_one::<usize>("max_vocab").copied(),
+                min_transition_count: compile_args
+                    .get_one::<usize>("min_transition_count")
+                    .copied()
+                    .unwrap_or(1),
+                map_to_unknown: compile_args.get_flag("unk"),
+            },
+            compile_args.get_one::<String>("classes"),
+            compile_args.get_one::<usize>("clusters"),
+            compile_args.get_one::<String>("members"),
         ),
         Some(("generate", generate_args)) => generate(
             generate_args.get_one::<String>("rules"),
-            generate_args.get_one::<usize>("tokens"),
+            generate_args.get_one::<String>("constraints"),
+            &GenerateOptions {
+                tokens: generate_args.get_one::<usize>("tokens").copied(),
+                tokenizer: generate_args.get_one::<String>("tokenizer").cloned(),
+                seed: generate_args.get_one::<u64>("seed").copied(),
+                heuristic: generate_args.get_one::<String>("heuristic").cloned(),
+                strict: generate_args.get_flag("strict"),
+                output_file: generate_args.get_one::<String>("output_file").cloned(),
+                output_dir: generate_args.get_one::<String>("output_dir").cloned(),
+                count: generate_args.get_one::<usize>("count").copied(),
+                name_template: generate_args.get_one::<String>("name_template").cloned(),
+                format: generate_args.get_one::<String>("format").cloned(),
+                continue_from: generate_args.get_one::<String>("continue_from").cloned(),
+                template: generate_args.get_one::<String>("template").cloned(),
+                jobs: generate_args.get_one::<usize>("jobs").copied(),
+                members: generate_args.get_one::<String>("members").cloned(),
+                sampler: generate_args.get_one::<String>("sampler").cloned(),
+                count_texts: generate_args.get_flag("count_texts"),
+            },
+        ),
+        Some(("repl", repl_args)) => repl(
+            repl_args.get_one::<String>("rules"),
+            repl_args.get_one::<usize>("tokens"),
+            repl_args.get_one::<u64>("seed"),
+        ),
+        Some(("serve", serve_args)) => serve(
+            serve_args
+                .get_many::<String>("rules")
+                .map(|rules| rules.collect())
+                .unwrap_or_default(),
+            serve_args.get_one::<String>("host"),
+            serve_args.get_one::<u16>("port"),
+        ),
+        Some(("stats", stats_args)) => stats(
+            stats_args.get_one::<String>("rules"),
+            stats_args.get_one::<usize>("top"),
+            stats_args.get_flag("json"),
+        ),
+        Some(("export-graph", export_args)) => export_graph(
+            export_args.get_one::<String>("rules"),
+            export_args.get_one::<String>("output_file"),
+            export_args.get_one::<String>("format"),
+            export_args.get_one::<String>("word"),
+            export_args.get_one::<usize>("hops"),
+            export_args.get_flag("counts"),
+        ),
+        Some(("edit", edit_args)) => edit(
+            edit_args.get_one::<String>("rules"),
+            edit_args.get_one::<String>("output_file"),
+            &get_edits(edit_args),
+        ),
+        Some(("diff", diff_args)) => diff(
+            diff_args.get_one::<String>("old"),
+            diff_args.get_one::<String>("new"),
+            diff_args.get_one::<usize>("top"),
+            diff_args.get_flag("json"),
         ),
         _ => unreachable!(),
     };
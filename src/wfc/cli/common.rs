@@ -0,0 +1,24 @@
+use std::fs::File;
+use std::io::{Error, Read};
+
+use crate::wfc::parsing::json_parse::{parse_members_json, parse_rules_json};
+use crate::wfc::wfc::classes::Members;
+use crate::wfc::wfc::rules::Rules;
+
+/// Reads the whole file into a string
+pub fn read_file(file_name: &str) -> Result<String, Error> {
+    let mut file = File::open(file_name)?;
+    let mut contents = String::new();
+    file.read_to_string(&mut contents)?;
+    Ok(contents)
+}
+
+/// Reads and parses a rules json file
+pub fn read_rules(file_name: &str) -> Result<Rules, Error> {
+    Ok(parse_rules_json(&read_file(file_name)?)?)
+}
+
+/// Reads and parses a json file of the words of every class
+pub fn read_members(file_name: &str) -> Result<Members, Error> {
+    Ok(parse_members_json(&read_file(file_name)?)?)
+}
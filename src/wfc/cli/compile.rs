@@ -1,34 +1,120 @@
-use std::io::ErrorKind::InvalidInput;
-use std::io::{Error, Write};
+use std::io::ErrorKind::{InvalidData, InvalidInput};
+use std::io::{stdin, BufRead, BufReader, Error, Write};
 use std::{fs::File, io::Read};
 
-use crate::wfc::parsing::json_parse::rules_to_json;
-use crate::wfc::parsing::text_parse::generate_rules;
+use rayon::prelude::*;
+
+use crate::wfc::parsing::class_parse::parse_class_map;
+use crate::wfc::parsing::code_parse::{generate_code_rules, Language};
+use crate::wfc::parsing::json_parse::{members_to_json, rules_to_json};
+use crate::wfc::parsing::midi_parse::generate_midi_rules;
+use crate::wfc::parsing::rules_builder::RulesBuilder;
+use crate::wfc::wfc::classes::{classify_rules, cluster_words};
+use crate::wfc::wfc::pruning::{prune_rules, PruneOptions};
+use crate::wfc::wfc::rules::{merge_rules, Rules};
+
+use super::common::read_file;
 
 static DEFAULT_OUTPUT_FILE: &str = "rules.json";
 
-/// Compiles a text file into rules json
+/// The input file name which reads from stdin instead
+static STDIN_FILE_NAME: &str = "-";
+
+/// Opens the input file, or stdin
+fn open_input(input_file_name: &str) -> Result<Box<dyn BufRead>, Error> {
+    match input_file_name == STDIN_FILE_NAME {
+        true => Ok(Box::new(stdin().lock())),
+        false => Ok(Box::new(BufReader::new(File::open(input_file_name)?))),
+    }
+}
+
+/// Compiles a single text (or midi or source code) file into rules
+fn compile_file(input_file_name: &str, tokenizer: Option<&str>) -> Result<Rules, Error> {
+    let mut input_file = open_input(input_file_name)?;
+
+    match tokenizer {
+        Some("midi") => {
+            let mut contents = Vec::<u8>::new();
+            input_file.read_to_end(&mut contents)?;
+            generate_midi_rules(&contents)
+        }
+        Some("code") => {
+            let mut contents = String::new();
+            input_file.read_to_string(&mut contents)?;
+            Ok(generate_code_rules(contents, Language::Rust))
+        }
+        Some("python") => {
+            let mut contents = String::new();
+            input_file.read_to_string(&mut contents)?;
+            Ok(generate_code_rules(contents, Language::Python))
+        }
+        _ => {
+            let mut builder = RulesBuilder::new();
+            builder.feed_reader(input_file)?;
+            Ok(builder.build())
+        }
+    }
+}
+
+/// Writes the string to the file
+fn write_file(file_name: &str, contents: &str) -> Result<(), Error> {
+    File::create(file_name)?.write_all(contents.as_bytes())
+}
+
+/// Compiles text (or midi or source code) files into rules json, pruning rare words and
+/// transitions. The files are compiled in parallel and text is compiled as it is read, so it
+/// doesn't have to fit in memory. With a class map file (or a number of clusters to group the
+/// words into) the rules are between the classes, and the words of every class are written to the
+/// members file.
 pub fn compile(
-    input_file_name: Option<&String>,
+    input_file_names: Vec<&String>,
     output_file_name: Option<&String>,
+    tokenizer: Option<&String>,
+    prune_options: &PruneOptions,
+    class_map_file_name: Option<&String>,
+    clusters: Option<&usize>,
+    members_file_name: Option<&String>,
 ) -> Result<(), Error> {
-    let input_file_name =
-        input_file_name.ok_or(Error::new(InvalidInput, "No input file was given"))?;
+    if input_file_names.is_empty() {
+        return Err(Error::new(InvalidInput, "No input file was given"));
+    }
+    if (class_map_file_name.is_some() || clusters.is_some()) && members_file_name.is_none() {
+        return Err(Error::new(
+            InvalidInput,
+            "Compiling with classes needs a members file",
+        ));
+    }
 
     let output_file_name = match output_file_name {
         Some(file) => file,
         None => DEFAULT_OUTPUT_FILE,
     };
 
-    let mut input_file = File::open(input_file_name)?;
-    let mut contents = String::new();
-    input_file.read_to_string(&mut contents)?;
-
-    let rules = generate_rules(contents);
-    let json = rules_to_json(&rules)?;
-
-    let mut output_file = File::create(output_file_name)?;
-    output_file.write_all(json.as_bytes())?;
-
-    Ok(())
+    let tokenizer = tokenizer.map(String::as_str);
+    let mut rules = input_file_names
+        .par_iter()
+        .map(|input_file_name| compile_file(input_file_name, tokenizer))
+        .try_reduce(Rules::new, |rules, other| Ok(merge_rules(rules, other)))?;
+    // The words are pruned before they are grouped into classes, so that the members only have
+    // the words kept
+    if *prune_options != PruneOptions::default() {
+        rules = prune_rules(&rules, prune_options);
+    }
+    let classes = match (class_map_file_name, clusters) {
+        (Some(file_name), _) => {
+            // Only the text tokenizer lowercases the words
+            let lowercase = matches!(tokenizer, None | Some("text"));
+            let classes = parse_class_map(&read_file(file_name)?, lowercase);
+            Some(classes.map_err(|e| Error::new(InvalidData, e))?)
+        }
+        (None, Some(clusters)) => Some(cluster_words(&rules, *clusters)),
+        (None, None) => None,
+    };
+    if let (Some(classes), Some(members_file_name)) = (classes, members_file_name) {
+        let members;
+        (rules, members) =
+            classify_rules(&rules, &classes).map_err(|e| Error::new(InvalidData, e))?;
+        write_file(members_file_name, &members_to_json(&members)?)?;
+    }
+    write_file(output_file_name, &rules_to_json(&rules)?)
 }
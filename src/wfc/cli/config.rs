@@ -0,0 +1,271 @@
+use clap::Command;
+use std::collections::BTreeMap;
+use std::io::Error;
+use std::io::ErrorKind::InvalidData;
+use toml::{Table, Value};
+
+use super::common::read_file;
+
+/// The section of the config holding the profiles
+static PROFILE_SECTION: &str = "profile";
+
+/// The options of one level of the config, the top level or a profile
+#[derive(Default)]
+struct Level {
+    /// The options outside of any subcommand table, set for every subcommand having them
+    shared: Table,
+    /// The options of every subcommand table
+    subcommands: BTreeMap<String, Table>,
+}
+
+impl Level {
+    /// Splits the table into the shared options and the subcommand tables
+    fn new(table: &Table) -> Self {
+        let mut level = Self::default();
+        for (key, value) in table {
+            match value {
+                Value::Table(options) => {
+                    level.subcommands.insert(key.clone(), options.clone());
+                }
+                value => {
+                    level.shared.insert(key.clone(), value.clone());
+                }
+            }
+        }
+        level
+    }
+}
+
+/// Returns the levels of the config from the lowest priority: the top level, then the profile if
+/// one is given
+fn get_levels(config: &Table, profile: Option<&str>) -> Result<Vec<Level>, Error> {
+    let mut top = config.clone();
+    let profiles = top.remove(PROFILE_SECTION);
+    let mut levels = vec![Level::new(&top)];
+
+    if let Some(profile) = profile {
+        let profile = profiles
+            .as_ref()
+            .and_then(|profiles| profiles.get(profile))
+            .and_then(Value::as_table)
+            .ok_or(Error::new(InvalidData, "The profile is not in the config"))?;
+        levels.push(Level::new(profile));
+    }
+
+    Ok(levels)
+}
+
+/// Returns the id of the argument of the command named by the key, its long flag or its name
+fn find_arg(command: &Command, key: &str) -> Option<String> {
+    command
+        .get_arguments()
+        .find(|arg| arg.get_long() == Some(key) || *arg.get_id() == key.replace('-', "_"))
+        .map(|arg| arg.get_id().to_string())
+}
+
+/// Returns the options of the levels for the subcommand, each level overriding the ones before and
+/// the options of the subcommand overriding the shared ones of the same level
+fn get_options(levels: &[Level], subcommand: &Command) -> Table {
+    let mut options = Table::new();
+    for level in levels {
+        let shared = level
+            .shared
+            .iter()
+            .filter(|(key, _)| find_arg(subcommand, key).is_some());
+        options.extend(shared.map(|(key, value)| (key.clone(), value.clone())));
+        if let Some(section) = level.subcommands.get(subcommand.get_name()) {
+            options.extend(section.clone());
+        }
+    }
+    options
+}
+
+/// Returns the value as command line values
+fn get_values(value: &Value) -> Result<Vec<String>, Error> {
+    match value {
+        Value::String(string) => Ok(vec![string.clone()]),
+        Value::Integer(integer) => Ok(vec![integer.to_string()]),
+        Value::Float(float) => Ok(vec![float.to_string()]),
+        Value::Boolean(boolean) => Ok(vec![boolean.to_string()]),
+        Value::Array(values) => Ok(values
+            .iter()
+            .map(get_values)
+            .collect::<Result<Vec<Vec<String>>, Error>>()?
+            .concat()),
+        _ => Err(Error::new(InvalidData, "Unsupported value in the config")),
+    }
+}
+
+/// Sets the options of the config section as the defaults of the subcommand, so that command line
+/// flags still override them. Options are named by their long flag or their name.
+fn apply_section(command: Command, options: &Table) -> Result<Command, Error> {
+    let mut command = command;
+
+    for (key, value) in options {
+        let id = find_arg(&command, key).ok_or(Error::new(
+            InvalidData,
+            format!("Unknown option {key} for {}", command.get_name()),
+        ))?;
+
+        let values = get_values(value)?;
+        command = command.mut_arg(id, |arg| arg.default_values(values));
+    }
+
+    Ok(command)
+}
+
+/// Sets the options of the parsed config as the defaults of the subcommands, see [apply_config]
+fn apply_table(command: Command, config: &Table, profile: Option<&str>) -> Result<Command, Error> {
+    let levels = get_levels(config, profile)?;
+    let subcommands: Vec<Command> = command.get_subcommands().cloned().collect();
+
+    for level in &levels {
+        if let Some(name) = level
+            .subcommands
+            .keys()
+            .find(|name| !subcommands.iter().any(|found| found.get_name() == *name))
+        {
+            return Err(Error::new(
+                InvalidData,
+                format!("Unknown subcommand {name} in the config"),
+            ));
+        }
+        if let Some(key) = level.shared.keys().find(|key| {
+            !subcommands
+                .iter()
+                .any(|found| find_arg(found, key).is_some())
+        }) {
+            return Err(Error::new(
+                InvalidData,
+                format!("Unknown option {key} in the config"),
+            ));
+        }
+    }
+
+    let mut command = command;
+    for found in subcommands {
+        let options = get_options(&levels, &found);
+        if options.is_empty() {
+            continue;
+        }
+        let name = found.get_name().to_string();
+        let updated = apply_section(found, &options)?;
+        command = command.mut_subcommand(name, |_| updated);
+    }
+
+    Ok(command)
+}
+
+/// Reads the toml config file and sets the options in it as the defaults of the subcommands. The
+/// options of a subcommand go in its table, options outside of any table apply to every subcommand
+/// having them. A profile given by name is read from [profile.<name>] the same way, overriding the
+/// rest of the config. Flags set by the config can be turned off with --flag=false.
+pub fn apply_config(
+    command: Command,
+    config_file_name: &str,
+    profile: Option<&String>,
+) -> Result<Command, Error> {
+    let config: Table = read_file(config_file_name)?
+        .parse()
+        .map_err(|e| Error::new(InvalidData, e))?;
+
+    apply_table(command, &config, profile.map(String::as_str))
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::{Arg, ArgAction, Command};
+    use toml::Table;
+
+    use super::{apply_section, apply_table};
+
+    static CONFIG: &str = r#"
+        tokenizer = "text"
+
+        [generate]
+        tokens = 100
+        tokenizer = "code"
+
+        [compile]
+        min-count = 2
+
+        [profile.names]
+        strict = true
+
+        [profile.names.generate]
+        tokens = 5
+    "#;
+
+    fn get_command() -> Command {
+        Command::new("wfc")
+            .subcommand(
+                Command::new("generate")
+                    .arg(Arg::new("tokens").short('t'))
+                    .arg(Arg::new("tokenizer").long("tokenizer"))
+                    .arg(
+                        Arg::new("strict")
+                            .long("strict")
+                            .action(ArgAction::Set)
+                            .num_args(0..=1)
+                            .require_equals(true)
+                            .default_value("false")
+                            .default_missing_value("true")
+                            .value_parser(clap::value_parser!(bool)),
+                    ),
+            )
+            .subcommand(
+                Command::new("compile")
+                    .arg(Arg::new("min_count").long("min-count"))
+                    .arg(Arg::new("tokenizer").long("tokenizer")),
+            )
+    }
+
+    #[test]
+    fn test_apply_table_sanity() {
+        let config: Table = CONFIG.parse().unwrap();
+        let command = apply_table(get_command(), &config, None).unwrap();
+
+        let matches = command.clone().get_matches_from(["wfc", "generate"]);
+        let generate = matches.subcommand_matches("generate").unwrap();
+        assert_eq!(generate.get_one::<String>("tokens").unwrap(), "100");
+        assert_eq!(generate.get_one::<String>("tokenizer").unwrap(), "code");
+        assert!(!generate.get_flag("strict"));
+
+        let matches = command.get_matches_from(["wfc", "compile"]);
+        let compile = matches.subcommand_matches("compile").unwrap();
+        assert_eq!(compile.get_one::<String>("min_count").unwrap(), "2");
+        assert_eq!(compile.get_one::<String>("tokenizer").unwrap(), "text");
+    }
+
+    #[test]
+    fn test_apply_table_profile() {
+        let config: Table = CONFIG.parse().unwrap();
+        let command = apply_table(get_command(), &config, Some("names")).unwrap();
+
+        let matches = command.clone().get_matches_from(["wfc", "generate"]);
+        let generate = matches.subcommand_matches("generate").unwrap();
+        assert_eq!(generate.get_one::<String>("tokens").unwrap(), "5");
+        assert_eq!(generate.get_one::<String>("tokenizer").unwrap(), "code");
+        assert!(generate.get_flag("strict"));
+
+        let matches = command.get_matches_from(["wfc", "generate", "-t", "7", "--strict=false"]);
+        let generate = matches.subcommand_matches("generate").unwrap();
+        assert_eq!(generate.get_one::<String>("tokens").unwrap(), "7");
+        assert!(!generate.get_flag("strict"));
+
+        assert!(apply_table(get_command(), &config, Some("other")).is_err());
+    }
+
+    #[test]
+    fn test_apply_table_unknown() {
+        let unknown_option: Table = "color = true".parse().unwrap();
+        assert!(apply_table(get_command(), &unknown_option, None).is_err());
+
+        let unknown_subcommand: Table = "[serve]\nport = 1".parse().unwrap();
+        assert!(apply_table(get_command(), &unknown_subcommand, None).is_err());
+
+        let generate = get_command().find_subcommand("generate").unwrap().clone();
+        let unknown_key: Table = "min-count = 2".parse().unwrap();
+        assert!(apply_section(generate, &unknown_key).is_err());
+    }
+}
@@ -0,0 +1,73 @@
+use std::io::Error;
+use std::io::ErrorKind::InvalidInput;
+
+use crate::wfc::wfc::diff::{diff_rules, RulesDiff};
+
+use super::common::read_rules;
+
+/// Formats the words as a readable list
+fn format_words(words: &[String]) -> String {
+    words
+        .iter()
+        .map(|word| format!("{:?}", word))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+/// Formats the differences as a human readable report
+fn format_diff(diff: &RulesDiff) -> String {
+    let mut lines = vec![
+        format!("added tokens: {}", diff.added_tokens.len()),
+        format!("  {}", format_words(&diff.added_tokens)),
+        format!("removed tokens: {}", diff.removed_tokens.len()),
+        format!("  {}", format_words(&diff.removed_tokens)),
+        format!("changed transitions: {} tokens", diff.transitions.len()),
+    ];
+    for transitions in &diff.transitions {
+        lines.push(format!("  {:?}", transitions.word));
+        if !transitions.added.is_empty() {
+            lines.push(format!("    + {}", format_words(&transitions.added)));
+        }
+        if !transitions.removed.is_empty() {
+            lines.push(format!("    - {}", format_words(&transitions.removed)));
+        }
+    }
+
+    if let Some(shifts) = &diff.shifts {
+        lines.push("biggest probability shifts:".to_string());
+        for shift in shifts {
+            lines.push(format!(
+                "  {:?} -> {:?}: {:.3} to {:.3}",
+                shift.before, shift.after, shift.old, shift.new
+            ));
+        }
+    }
+
+    lines.join("\n") + "\n"
+}
+
+/// Prints what changed from the old rules file to the new one, as text or json
+pub fn diff(
+    old_file_name: Option<&String>,
+    new_file_name: Option<&String>,
+    top: Option<&usize>,
+    json: bool,
+) -> Result<(), Error> {
+    let old_file_name =
+        old_file_name.ok_or(Error::new(InvalidInput, "No old rules file was given"))?;
+    let new_file_name =
+        new_file_name.ok_or(Error::new(InvalidInput, "No new rules file was given"))?;
+
+    let diff = diff_rules(
+        &read_rules(old_file_name)?,
+        &read_rules(new_file_name)?,
+        *top.unwrap_or(&10),
+    );
+
+    match json {
+        true => println!("{}", serde_json::to_string(&diff)?),
+        false => print!("{}", format_diff(&diff)),
+    }
+
+    Ok(())
+}
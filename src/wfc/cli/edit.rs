@@ -0,0 +1,28 @@
+use std::fs::File;
+use std::io::ErrorKind::{InvalidData, InvalidInput};
+use std::io::{Error, Write};
+
+use crate::wfc::parsing::json_parse::rules_to_json;
+use crate::wfc::wfc::edit::{apply_edit, validate_rules, Edit};
+
+use super::common::read_rules;
+
+/// Applies the edits to the rules file in order, writing the result to the output file (or back to
+/// the rules file) only if the rules are valid once every edit is applied
+pub fn edit(
+    rules_file_name: Option<&String>,
+    output_file_name: Option<&String>,
+    edits: &[Edit],
+) -> Result<(), Error> {
+    let rules_file_name =
+        rules_file_name.ok_or(Error::new(InvalidInput, "No rules file was given"))?;
+    let mut rules = read_rules(rules_file_name)?;
+
+    for edit in edits {
+        apply_edit(&mut rules, edit).map_err(|e| Error::new(InvalidInput, e))?;
+    }
+    validate_rules(&rules).map_err(|e| Error::new(InvalidData, e))?;
+
+    let output_file_name = output_file_name.unwrap_or(rules_file_name);
+    File::create(output_file_name)?.write_all(rules_to_json(&rules)?.as_bytes())
+}
@@ -0,0 +1,45 @@
+use std::fs::File;
+use std::io::ErrorKind::InvalidInput;
+use std::io::{Error, Write};
+
+use crate::wfc::parsing::graph_export::{
+    get_neighborhood, rules_to_dot, rules_to_graph_json, rules_to_graphml,
+};
+
+use super::common::read_rules;
+
+/// Exports the rules as a transition graph, optionally only around the given word
+pub fn export_graph(
+    rules_file_name: Option<&String>,
+    output_file_name: Option<&String>,
+    format: Option<&String>,
+    word: Option<&String>,
+    hops: Option<&usize>,
+    with_counts: bool,
+) -> Result<(), Error> {
+    let rules_file_name =
+        rules_file_name.ok_or(Error::new(InvalidInput, "No rules file was given"))?;
+
+    let rules = read_rules(rules_file_name)?;
+    let words = match word {
+        Some(word) if !rules.contains_key(word) => {
+            return Err(Error::new(InvalidInput, "The word is not in the rules"))
+        }
+        Some(word) => Some(get_neighborhood(&rules, word, *hops.unwrap_or(&1))),
+        None => None,
+    };
+
+    let graph = match format.map(String::as_str) {
+        Some("graphml") => rules_to_graphml(&rules, words.as_ref(), with_counts),
+        Some("json") => rules_to_graph_json(&rules, words.as_ref(), with_counts)?,
+        _ => rules_to_dot(&rules, words.as_ref(), with_counts),
+    };
+
+    match output_file_name {
+        Some(file_name) => File::create(file_name)?.write_all(graph.as_bytes()),
+        None => {
+            print!("{}", graph);
+            Ok(())
+        }
+    }
+}
@@ -1,32 +1,346 @@
-use std::fs::File;
+use serde::Serialize;
+use std::fs::{create_dir_all, File};
 use std::io::ErrorKind::{InvalidData, InvalidInput};
-use std::io::{Error, Read};
+use std::io::{stdout, Error, Write};
+use std::path::Path;
 
-use crate::wfc::parsing::json_parse::parse_rules_json;
-use crate::wfc::parsing::post_processing::merge;
-use crate::wfc::wfc::algorithm::iterate;
-use crate::wfc::wfc::rules::generate_wfc_vector;
+use crate::wfc::parsing::json_parse::parse_constraints_json;
+use crate::wfc::parsing::post_processing::{merge, merge_code, merge_midi};
+use crate::wfc::parsing::template::parse_template;
+use crate::wfc::parsing::text_parse::tokenize;
+use crate::wfc::wfc::algorithm::{verify, Heuristic};
+use crate::wfc::wfc::classes::{get_class, members_to_class_map, ClassMap};
+use crate::wfc::wfc::constraints::Constraints;
+use crate::wfc::wfc::feasibility::Feasibility;
+use crate::wfc::wfc::generator::{with_start, Generator};
+use crate::wfc::wfc::rules::{Rules, START};
+use crate::wfc::wfc::sampler::Sampler;
 
-/// Generates a text with [tokens] tokens and rules from the given file
-pub fn generate(rules_file_name: Option<&String>, tokens: Option<&usize>) -> Result<(), Error> {
+use super::common::{read_file, read_members, read_rules};
+
+/// The name of the sample files in the output directory when no template is given
+static DEFAULT_NAME_TEMPLATE: &str = "sample_{n}.{ext}";
+
+/// Returns an error describing the adjacent words of the tokens which the rules don't allow
+fn check_strict(tokens: &[String], rules: &Rules, name: &str) -> Result<(), Error> {
+    let invalid = verify(tokens, rules);
+    if invalid.is_empty() {
+        return Ok(());
+    }
+
+    let pairs: Vec<String> = invalid
+        .iter()
+        .map(|pair| format!("{:?} -> {:?} at {}", pair.before, pair.after, pair.index))
+        .collect();
+    Err(Error::new(
+        InvalidData,
+        format!(
+            "{name} has {} invalid adjacencies: {}",
+            invalid.len(),
+            pairs.join(", ")
+        ),
+    ))
+}
+
+/// Reads and tokenizes the text to continue, failing if the rules can't generate it (or the classes
+/// of its words, when the rules are between classes)
+fn read_prefix(file_name: &str, rules: &Rules, classes: &ClassMap) -> Result<Vec<String>, Error> {
+    let tokens = tokenize(&read_file(file_name)?);
+
+    let mut unknown: Vec<String> = tokens
+        .iter()
+        .filter(|token| !rules.contains_key(get_class(token, classes)))
+        .map(|token| format!("{:?}", token))
+        .collect();
+    unknown.sort();
+    unknown.dedup();
+    if !unknown.is_empty() {
+        return Err(Error::new(
+            InvalidData,
+            format!(
+                "The text to continue has tokens which are not in the rules: {}",
+                unknown.join(", ")
+            ),
+        ));
+    }
+
+    let class_tokens: Vec<String> = tokens
+        .iter()
+        .map(|token| get_class(token, classes).to_string())
+        .collect();
+    check_strict(&with_start(&class_tokens), rules, "The text to continue")?;
+    Ok(tokens)
+}
+
+/// Returns an error suggesting the nearest numbers of tokens which fit the rules if no text of
+/// [tokens] tokens (after the prefix of the generator) does
+fn check_length(generator: &Generator, tokens: usize) -> Result<(), Error> {
+    let from = generator
+        .prefix()
+        .last()
+        .map_or(START, |word| generator.get_token(word));
+    let feasibility = Feasibility::new(generator.compiled_rules(), from);
+    if feasibility.is_feasible(tokens) {
+        return Ok(());
+    }
+
+    let suggestion = match feasibility.get_nearest(tokens) {
+        (Some(shorter), Some(longer)) => {
+            format!(", the nearest lengths which do are {shorter} and {longer}")
+        }
+        (Some(length), None) | (None, Some(length)) => {
+            format!(", the nearest length which does is {length}")
+        }
+        (None, None) => ", the rules can't generate any text".to_string(),
+    };
+    Err(Error::new(
+        InvalidInput,
+        format!("No text of {tokens} tokens fits the rules{suggestion}"),
+    ))
+}
+
+/// The options of the generate subcommand
+#[derive(Default)]
+pub struct GenerateOptions {
+    /// How many tokens to generate
+    pub tokens: Option<usize>,
+    /// How to turn the tokens back into an output: text, midi or code
+    pub tokenizer: Option<String>,
+    pub seed: Option<u64>,
+    /// How the next token to collapse is chosen, see [Heuristic]
+    pub heuristic: Option<String>,
+    /// Fail if the result breaks the rules
+    pub strict: bool,
+    /// The file to write the sample to, instead of stdout
+    pub output_file: Option<String>,
+    /// The directory to write every sample to, one file per sample
+    pub output_dir: Option<String>,
+    /// How many samples to generate
+    pub count: Option<usize>,
+    /// The name of the files in the output directory, see [get_file_name]
+    pub name_template: Option<String>,
+    /// How to write the samples: text (merged tokens), json or tokens
+    pub format: Option<String>,
+    /// A text file every sample continues, generating the tokens after it
+    pub continue_from: Option<String>,
+    /// A text with blanks to fill, see [parse_template]
+    pub template: Option<String>,
+    /// How many threads generate the samples, all the cores if not given
+    pub jobs: Option<usize>,
+    /// A json file with the words of every class, for rules compiled with classes
+    pub members: Option<String>,
+    /// How the words are chosen, see [Sampler]
+    pub sampler: Option<String>,
+    /// Print how many texts fit the rules instead of generating any
+    pub count_texts: bool,
+}
+
+/// A sample as written in the json format
+#[derive(Serialize)]
+struct Sample<'a> {
+    tokens: &'a [String],
+    /// The merged tokens, except for midi which is binary
+    #[serde(skip_serializing_if = "Option::is_none")]
+    text: Option<String>,
+}
+
+/// Returns the sample written in the given format, merging it back with the tokenizer
+fn format_sample(tokens: Vec<String>, tokenizer: &str, format: &str) -> Result<Vec<u8>, Error> {
+    match format {
+        "tokens" => Ok((serde_json::to_string(&tokens)? + "\n").into_bytes()),
+        "json" => {
+            let text = match tokenizer {
+                "midi" => None,
+                "code" | "python" => Some(merge_code(tokens.clone())),
+                _ => Some(merge(tokens.clone())),
+            };
+            let sample = Sample {
+                tokens: &tokens,
+                text,
+            };
+            Ok((serde_json::to_string(&sample)? + "\n").into_bytes())
+        }
+        _ => match tokenizer {
+            "midi" => Ok(merge_midi(tokens)),
+            "code" | "python" => Ok(merge_code(tokens).into_bytes()),
+            _ => Ok(merge(tokens).into_bytes()),
+        },
+    }
+}
+
+/// Returns the file name of a sample from the template, replacing {n} with the number of the
+/// sample (padded to the same width for every sample) and {ext} with the extension of the format
+fn get_file_name(
+    template: &str,
+    number: usize,
+    count: usize,
+    tokenizer: &str,
+    format: &str,
+) -> String {
+    let width = count.to_string().len();
+    let extension = match (format, tokenizer) {
+        ("json" | "tokens", _) => "json",
+        (_, "midi") => "mid",
+        _ => "txt",
+    };
+
+    template
+        .replace("{n}", &format!("{number:0width$}"))
+        .replace("{ext}", extension)
+}
+
+/// Generates texts (or midi or source code) with rules from the given file, satisfying the
+/// constraints file if given, and writes them to stdout, a file or a directory
+pub fn generate(
+    rules_file_name: Option<&String>,
+    constraints_file_name: Option<&String>,
+    options: &GenerateOptions,
+) -> Result<(), Error> {
     let rules_file_name =
         rules_file_name.ok_or(Error::new(InvalidInput, "No rules file was given"))?;
-    let tokens = tokens.ok_or(Error::new(InvalidInput, "Field tokens is empty"))?;
+    let template_parts = match &options.template {
+        Some(template) => Some(parse_template(template).map_err(|e| Error::new(InvalidInput, e))?),
+        None => None,
+    };
+    let tokens = match (options.tokens, &template_parts) {
+        (Some(tokens), _) => tokens,
+        (None, Some(_)) => 0,
+        (None, None) => return Err(Error::new(InvalidInput, "Field tokens is empty")),
+    };
+    let tokenizer = options.tokenizer.as_deref().unwrap_or("text");
+    if template_parts.is_some() && tokenizer != "text" {
+        return Err(Error::new(
+            InvalidInput,
+            "Only text templates can be filled",
+        ));
+    }
+    let format = options.format.as_deref().unwrap_or("text");
+    let count = options.count.unwrap_or(1);
+    if count > 1
+        && options.output_dir.is_none()
+        && (tokenizer == "midi" || options.output_file.is_some())
+    {
+        return Err(Error::new(
+            InvalidInput,
+            "Several samples need an output directory",
+        ));
+    }
 
-    let mut input_file = File::open(rules_file_name)?;
-    let mut contents = String::new();
-    input_file.read_to_string(&mut contents)?;
+    let rules = read_rules(rules_file_name)?;
+    let members = match &options.members {
+        Some(file_name) => Some(read_members(file_name)?),
+        None => None,
+    };
+    let classes = members
+        .as_ref()
+        .map(members_to_class_map)
+        .unwrap_or_default();
+    let prefix = match &options.continue_from {
+        Some(_) if tokenizer != "text" => {
+            return Err(Error::new(InvalidInput, "Only text can be continued"))
+        }
+        Some(file_name) => read_prefix(file_name, &rules, &classes)?,
+        None => Vec::new(),
+    };
+    let constraints = match constraints_file_name {
+        Some(file_name) => parse_constraints_json(&read_file(file_name)?)?,
+        None => Constraints::default(),
+    };
+    let heuristic = match &options.heuristic {
+        Some(name) => name
+            .parse::<Heuristic>()
+            .map_err(|e| Error::new(InvalidInput, e))?,
+        None => Heuristic::default(),
+    };
+    let sampler = match &options.sampler {
+        Some(name) => name
+            .parse::<Sampler>()
+            .map_err(|e| Error::new(InvalidInput, e))?,
+        None => Sampler::default(),
+    };
 
-    let rules = parse_rules_json(&contents)?;
-    let vector = generate_wfc_vector(&rules, *tokens);
+    let mut builder = Generator::from_rules(rules)
+        .length(tokens)
+        .constraints(constraints)
+        .heuristic(heuristic)
+        .sampler(sampler);
+    if let Some(seed) = options.seed {
+        builder = builder.seed(seed);
+    }
+    if let Some(members) = members {
+        builder = builder.members(members);
+    }
+    let mut generator = builder.build().map_err(|e| Error::new(InvalidData, e))?;
+    generator
+        .set_prefix(prefix)
+        .map_err(|e| Error::new(InvalidData, e))?;
+    // Counting comes before the length is checked, as no text fitting is a count of 0
+    if options.count_texts {
+        match generator.count().map_err(|e| Error::new(InvalidData, e))? {
+            Some(count) => println!("{count}"),
+            None => println!("more than {}", u128::MAX),
+        }
+        return Ok(());
+    }
+    if template_parts.is_none() {
+        check_length(&generator, tokens)?;
+    }
+
+    let template = options
+        .name_template
+        .as_deref()
+        .unwrap_or(DEFAULT_NAME_TEMPLATE);
+    if let Some(output_dir) = &options.output_dir {
+        if count > 1 && !template.contains("{n}") {
+            return Err(Error::new(
+                InvalidInput,
+                "The name template needs {n} to write several samples",
+            ));
+        }
+        create_dir_all(output_dir)?;
+    }
+
+    let results = generator
+        .generate_batch(count, options.jobs.unwrap_or(0), template_parts.as_deref())
+        .map_err(|e| Error::new(InvalidData, e))?;
 
-    let result = iterate(vector, &rules);
+    for (result, number) in results.into_iter().zip(1..) {
+        if options.strict {
+            let result_classes: Vec<String> = result
+                .iter()
+                .map(|word| get_class(word, &classes).to_string())
+                .collect();
+            check_strict(&result_classes, generator.rules(), "The result")?;
+        }
+        let sample = format_sample(result, tokenizer, format)?;
 
-    match result {
-        Ok(result) => {
-            print!("{}", merge(result));
-            Ok(())
+        match (&options.output_dir, &options.output_file) {
+            (Some(output_dir), _) => {
+                let file_name = get_file_name(template, number, count, tokenizer, format);
+                File::create(Path::new(output_dir).join(file_name))?.write_all(&sample)?;
+            }
+            (None, Some(output_file)) => File::create(output_file)?.write_all(&sample)?,
+            (None, None) => stdout().write_all(&sample)?,
         }
-        Err(e) => Err(Error::new(InvalidData, e)),
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::get_file_name;
+
+    #[test]
+    fn test_get_file_name_sanity() {
+        assert_eq!(
+            get_file_name("sample_{n}.{ext}", 7, 120, "text", "text"),
+            "sample_007.txt"
+        );
+        assert_eq!(get_file_name("{n}.{ext}", 2, 3, "midi", "text"), "2.mid");
+        assert_eq!(
+            get_file_name("out-{n}.{ext}", 1, 1, "midi", "tokens"),
+            "out-1.json"
+        );
     }
 }
@@ -0,0 +1,258 @@
+use std::collections::HashSet;
+use std::io::ErrorKind::{InvalidData, InvalidInput};
+use std::io::{stdin, stdout, BufRead, Error, Write};
+
+use crate::wfc::parsing::post_processing::merge;
+use crate::wfc::wfc::generator::Generator;
+use crate::wfc::wfc::propagation::PLACEHOLDER;
+
+use super::common::read_rules;
+
+/// How many words of a set are listed before the rest is only counted
+const MAX_LISTED_WORDS: usize = 10;
+
+static HELP: &str = "commands:
+  gen [tokens]   generate a text, with the given number of tokens if given
+  seed <seed>    restart the random generator from the seed
+  pin <i> <word> force token i (starting from 1) to be the word
+  unpin <i>      remove the pin of token i
+  pins           list the pinned tokens
+  next <word>    list the words allowed after the word
+  why <i>        explain which words token i can be
+  reload         read the rules file again
+  help           show this message
+  quit           exit";
+
+/// The state kept between the commands of the repl
+struct Repl {
+    rules_file_name: String,
+    length: usize,
+    seed: Option<u64>,
+    generator: Generator,
+}
+
+/// Returns a readable list of the words, only listing the first few
+fn format_words(words: &HashSet<String>) -> String {
+    if words.contains(&PLACEHOLDER) {
+        return "nothing (contradiction)".to_string();
+    }
+
+    let mut sorted: Vec<&String> = words.iter().collect();
+    sorted.sort();
+    let mut listed: Vec<String> = sorted
+        .iter()
+        .take(MAX_LISTED_WORDS)
+        .map(|word| format!("{:?}", word))
+        .collect();
+    if sorted.len() > MAX_LISTED_WORDS {
+        listed.push(format!("and {} more", sorted.len() - MAX_LISTED_WORDS));
+    }
+
+    let plural = match words.len() {
+        1 => "",
+        _ => "s",
+    };
+    format!("{} word{plural}: {}", words.len(), listed.join(", "))
+}
+
+/// Parses a command argument, naming it in the error
+fn parse_argument<T: std::str::FromStr>(argument: Option<&str>, name: &str) -> Result<T, Error> {
+    argument
+        .and_then(|argument| argument.parse().ok())
+        .ok_or(Error::new(InvalidInput, format!("Expected {name}")))
+}
+
+impl Repl {
+    /// Builds a generator for the rules file, keeping the seed and the pins of the current one
+    fn build_generator(
+        rules_file_name: &str,
+        length: usize,
+        seed: Option<u64>,
+        previous: Option<&Generator>,
+    ) -> Result<Generator, Error> {
+        let mut builder = Generator::from_rules(read_rules(rules_file_name)?).length(length);
+        if let Some(seed) = seed {
+            builder = builder.seed(seed);
+        }
+        let mut generator = builder.build().map_err(|e| Error::new(InvalidData, e))?;
+
+        if let Some(previous) = previous {
+            for (index, word) in previous.pins() {
+                if generator.pin(*index, word).is_err() {
+                    eprintln!("Unpinned token {index}, {word:?} is no longer in the rules");
+                }
+            }
+        }
+
+        Ok(generator)
+    }
+
+    /// Explains which words the token at the index can be, based on its neighbors
+    fn why(&self, index: usize) -> Result<String, Error> {
+        if index == 0 || index > self.length {
+            return Err(Error::new(InvalidInput, "The token is out of range"));
+        }
+        let domains = self
+            .generator
+            .domains()
+            .map_err(|e| Error::new(InvalidInput, e))?;
+
+        let describe = |index: usize| {
+            let pinned = match self.generator.pins().contains_key(&index) {
+                true => " (pinned)",
+                false => "",
+            };
+            match index {
+                0 => "the start".to_string(),
+                index if index == domains.len() - 1 => "the end".to_string(),
+                index => format!("{}{pinned}", format_words(&domains[index])),
+            }
+        };
+
+        Ok([
+            format!("token {index} can be {}", describe(index)),
+            format!(
+                "  it must follow token {}: {}",
+                index - 1,
+                describe(index - 1)
+            ),
+            format!(
+                "  it must precede token {}: {}",
+                index + 1,
+                describe(index + 1)
+            ),
+        ]
+        .join("\n"))
+    }
+
+    /// Lists the words allowed after the word, the most frequent first
+    fn next(&self, word: &str) -> Result<String, Error> {
+        let allowed = self
+            .generator
+            .rules()
+            .get(word)
+            .ok_or(Error::new(InvalidInput, "The word is not in the rules"))?;
+
+        let mut after: Vec<(&String, usize)> = allowed
+            .after
+            .iter()
+            .map(|word| (word, allowed.counts.get(word).copied().unwrap_or(0)))
+            .collect();
+        after.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then(a.cmp(b)));
+
+        let lines: Vec<String> = after
+            .iter()
+            .map(|(word, count)| match count {
+                0 => format!("  {:?}", word),
+                count => format!("  {:?}: {}", word, count),
+            })
+            .collect();
+        Ok(format!("{} words:\n{}", after.len(), lines.join("\n")))
+    }
+
+    /// Executes a single command, returning what to print or None to exit
+    fn execute(&mut self, line: &str) -> Result<Option<String>, Error> {
+        let mut arguments = line.split_whitespace();
+        let command = match arguments.next() {
+            Some(command) => command,
+            None => return Ok(Some(String::new())),
+        };
+
+        let output = match command {
+            "gen" => {
+                if let Some(length) = arguments.next() {
+                    self.length = parse_argument(Some(length), "a number of tokens")?;
+                    self.generator.set_length(self.length);
+                }
+                let tokens = self
+                    .generator
+                    .generate_tokens()
+                    .map_err(|e| Error::new(InvalidData, e))?;
+                merge(tokens)
+            }
+            "seed" => {
+                let seed = parse_argument(arguments.next(), "a seed")?;
+                self.seed = Some(seed);
+                self.generator.set_seed(seed);
+                format!("seed set to {seed}")
+            }
+            "pin" => {
+                let index = parse_argument(arguments.next(), "a token index")?;
+                let word: String = parse_argument(arguments.next(), "a word")?;
+                self.generator
+                    .pin(index, &word)
+                    .map_err(|e| Error::new(InvalidInput, e))?;
+                format!("pinned token {index} to {word:?}")
+            }
+            "unpin" => {
+                let index = parse_argument(arguments.next(), "a token index")?;
+                match self.generator.unpin(index) {
+                    true => format!("unpinned token {index}"),
+                    false => format!("token {index} was not pinned"),
+                }
+            }
+            "pins" => self
+                .generator
+                .pins()
+                .iter()
+                .map(|(index, word)| format!("{index}: {word:?}"))
+                .collect::<Vec<String>>()
+                .join("\n"),
+            "next" => self.next(&parse_argument::<String>(arguments.next(), "a word")?)?,
+            "why" => self.why(parse_argument(arguments.next(), "a token index")?)?,
+            "reload" => {
+                self.generator = Self::build_generator(
+                    &self.rules_file_name,
+                    self.length,
+                    self.seed,
+                    Some(&self.generator),
+                )?;
+                format!("reloaded {}", self.rules_file_name)
+            }
+            "help" => HELP.to_string(),
+            "quit" | "exit" => return Ok(None),
+            _ => return Err(Error::new(InvalidInput, "Unknown command, try help")),
+        };
+
+        Ok(Some(output))
+    }
+}
+
+/// Runs an interactive prompt generating texts from the rules in the given file, keeping the rules
+/// loaded between commands
+pub fn repl(
+    rules_file_name: Option<&String>,
+    tokens: Option<&usize>,
+    seed: Option<&u64>,
+) -> Result<(), Error> {
+    let rules_file_name =
+        rules_file_name.ok_or(Error::new(InvalidInput, "No rules file was given"))?;
+    let length = *tokens.unwrap_or(&20);
+    let seed = seed.copied();
+
+    let mut repl = Repl {
+        rules_file_name: rules_file_name.clone(),
+        length,
+        seed,
+        generator: Repl::build_generator(rules_file_name, length, seed, None)?,
+    };
+
+    let mut lines = stdin().lock().lines();
+    loop {
+        print!("> ");
+        stdout().flush()?;
+
+        let line = match lines.next() {
+            Some(line) => line?,
+            None => break,
+        };
+        match repl.execute(&line) {
+            Ok(Some(output)) if output.is_empty() => (),
+            Ok(Some(output)) => println!("{}", output),
+            Ok(None) => break,
+            Err(e) => eprintln!("{}", e),
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,217 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::metadata;
+use std::io::ErrorKind::{InvalidData, InvalidInput, Other};
+use std::io::{Error, Read};
+use std::time::SystemTime;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::wfc::parsing::post_processing::merge;
+use crate::wfc::parsing::text_parse::tokenize;
+use crate::wfc::wfc::generator::Generator;
+
+use super::common::read_rules;
+
+/// The name of a rule set given without a name
+static DEFAULT_RULES_NAME: &str = "default";
+/// The number of tokens generated when a request doesn't give a length
+const DEFAULT_LENGTH: usize = 20;
+/// The most texts a single request can generate
+const MAX_COUNT: usize = 100;
+/// The most tokens a single text can have
+const MAX_LENGTH: usize = 10_000;
+/// The most bytes the body of a request can have
+const MAX_BODY_SIZE: u64 = 64 * 1024;
+
+/// A rules file kept in memory, reloaded when the file changes
+struct RuleSet {
+    file_name: String,
+    modified: Option<SystemTime>,
+    generator: Generator,
+}
+
+/// The body of a generation request
+#[derive(Deserialize)]
+struct GenerateRequest {
+    /// The name of the rule set, can be left out if there is only one
+    rules: Option<String>,
+    length: Option<usize>,
+    seed: Option<u64>,
+    /// The text every generated text starts with
+    prefix: Option<String>,
+    count: Option<usize>,
+}
+
+#[derive(Serialize)]
+struct GenerateResponse {
+    texts: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct ErrorResponse {
+    error: String,
+}
+
+/// Returns the last time the file was modified, if the platform knows it
+fn get_modified(file_name: &str) -> Option<SystemTime> {
+    metadata(file_name).and_then(|data| data.modified()).ok()
+}
+
+impl RuleSet {
+    /// Loads the rules file
+    fn load(file_name: &str) -> Result<Self, Error> {
+        let modified = get_modified(file_name);
+        let generator = Generator::from_rules(read_rules(file_name)?)
+            .build()
+            .map_err(|e| Error::new(InvalidData, e))?;
+
+        Ok(Self {
+            file_name: file_name.to_string(),
+            modified,
+            generator,
+        })
+    }
+
+    /// Loads the rules file again if it was modified since it was loaded, keeping the current
+    /// rules if the new ones are invalid
+    fn reload_if_modified(&mut self) {
+        if get_modified(&self.file_name) == self.modified {
+            return;
+        }
+        match Self::load(&self.file_name) {
+            Ok(rule_set) => {
+                *self = rule_set;
+                eprintln!("Reloaded {}", self.file_name);
+            }
+            Err(e) => eprintln!("Could not reload {}: {}", self.file_name, e),
+        }
+    }
+
+    /// Generates the texts asked for by the request
+    fn generate(&mut self, request: &GenerateRequest) -> Result<Vec<String>, &'static str> {
+        let count = request.count.unwrap_or(1);
+        if count > MAX_COUNT {
+            return Err("Too many texts were asked for");
+        }
+        let length = request.length.unwrap_or(DEFAULT_LENGTH);
+        if length > MAX_LENGTH {
+            return Err("Too many tokens were asked for");
+        }
+
+        let prefix = request.prefix.as_deref().map(tokenize).unwrap_or_default();
+        self.generator.set_prefix(prefix)?;
+        self.generator.set_length(length);
+        // Requests without a seed don't continue the sequence of a seeded one
+        self.generator
+            .set_seed(request.seed.unwrap_or_else(rand::random));
+
+        (0..count)
+            .map(|_| self.generator.generate_tokens().map(merge))
+            .collect()
+    }
+}
+
+/// Parses a rules argument, either a file name or name=file name
+fn parse_rules_argument(argument: &str) -> (String, String) {
+    match argument.split_once('=') {
+        Some((name, file_name)) => (name.to_string(), file_name.to_string()),
+        None => (DEFAULT_RULES_NAME.to_string(), argument.to_string()),
+    }
+}
+
+/// Responds with the value as json
+fn respond(request: Request, status: u16, body: &impl Serialize) -> Result<(), Error> {
+    let json = serde_json::to_string(body)?;
+    let header = Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..])
+        .map_err(|_| Error::new(Other, "Invalid header"))?;
+
+    request.respond(
+        Response::from_string(json)
+            .with_status_code(status)
+            .with_header(header),
+    )
+}
+
+/// Responds with an error message as json
+fn respond_error(request: Request, status: u16, error: &str) -> Result<(), Error> {
+    let error = ErrorResponse {
+        error: error.to_string(),
+    };
+    respond(request, status, &error)
+}
+
+/// Handles a generation request, generating with the rule set it names
+fn handle_generate(
+    mut request: Request,
+    rule_sets: &mut BTreeMap<String, RuleSet>,
+) -> Result<(), Error> {
+    let mut body = String::new();
+    Read::take(request.as_reader(), MAX_BODY_SIZE + 1).read_to_string(&mut body)?;
+    if body.len() as u64 > MAX_BODY_SIZE {
+        return respond_error(request, 413, "The request body is too large");
+    }
+    let body: GenerateRequest = match serde_json::from_str(&body) {
+        Ok(body) => body,
+        Err(e) => return respond_error(request, 400, &e.to_string()),
+    };
+
+    let rule_set = match &body.rules {
+        Some(name) => rule_sets.get_mut(name),
+        None if rule_sets.len() == 1 => rule_sets.values_mut().next(),
+        None => rule_sets.get_mut(DEFAULT_RULES_NAME),
+    };
+    let rule_set = match rule_set {
+        Some(rule_set) => rule_set,
+        None => return respond_error(request, 404, "No rules with this name"),
+    };
+
+    rule_set.reload_if_modified();
+    match rule_set.generate(&body) {
+        Ok(texts) => respond(request, 200, &GenerateResponse { texts }),
+        Err(e) => respond_error(request, 400, e),
+    }
+}
+
+/// Serves generation over http with the given rules files (each either a file name or
+/// name=file name), until the process is stopped
+pub fn serve(rules: Vec<&String>, host: Option<&String>, port: Option<&u16>) -> Result<(), Error> {
+    if rules.is_empty() {
+        return Err(Error::new(InvalidInput, "No rules file was given"));
+    }
+
+    let mut rule_sets = BTreeMap::<String, RuleSet>::new();
+    for argument in rules {
+        let (name, file_name) = parse_rules_argument(argument);
+        if rule_sets.contains_key(&name) {
+            return Err(Error::new(
+                InvalidInput,
+                format!("Two rules files are named {name}"),
+            ));
+        }
+        rule_sets.insert(name, RuleSet::load(&file_name)?);
+    }
+
+    let address = format!(
+        "{}:{}",
+        host.map(String::as_str).unwrap_or("127.0.0.1"),
+        port.unwrap_or(&8080)
+    );
+    let server = Server::http(&address).map_err(|e| Error::new(Other, e))?;
+    eprintln!("Listening on http://{address}");
+
+    for request in server.incoming_requests() {
+        let result = match (request.method(), request.url()) {
+            (Method::Post, "/generate") => handle_generate(request, &mut rule_sets),
+            (Method::Get, "/rules") => {
+                let names: Vec<&String> = rule_sets.keys().collect();
+                respond(request, 200, &names)
+            }
+            _ => respond_error(request, 404, "Not found"),
+        };
+        if let Err(e) = result {
+            eprintln!("{}", e);
+        }
+    }
+
+    Ok(())
+}
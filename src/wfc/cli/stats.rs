@@ -0,0 +1,68 @@
+use std::io::Error;
+use std::io::ErrorKind::InvalidInput;
+
+use crate::wfc::wfc::stats::{get_rules_stats, RulesStats};
+
+use super::common::read_rules;
+
+/// Formats the statistics as a human readable report
+fn format_stats(stats: &RulesStats) -> String {
+    let mut lines = vec![
+        format!("vocabulary size: {}", stats.vocabulary_size),
+        format!("transitions: {}", stats.transitions),
+        format!(
+            "words after a word: average {:.2}, median {}",
+            stats.average_after, stats.median_after
+        ),
+        format!(
+            "words before a word: average {:.2}, median {}",
+            stats.average_before, stats.median_before
+        ),
+        "most connected words:".to_string(),
+    ];
+    for degree in &stats.most_connected {
+        lines.push(format!(
+            "  {:?}: {} before, {} after",
+            degree.word, degree.before, degree.after
+        ));
+    }
+
+    lines.push(format!("dead ends: {}", stats.dead_ends.len()));
+    for word in &stats.dead_ends {
+        lines.push(format!("  {:?}", word));
+    }
+
+    if let Some(entropy) = &stats.entropy {
+        let average = entropy.values().sum::<f64>() / entropy.len().max(1) as f64;
+        lines.push(format!("average next word entropy: {:.3} bits", average));
+
+        let mut highest: Vec<(&String, &f64)> = entropy.iter().collect();
+        highest.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+        lines.push("highest next word entropy:".to_string());
+        for (word, bits) in highest.iter().take(stats.most_connected.len()) {
+            lines.push(format!("  {:?}: {:.3} bits", word, bits));
+        }
+    }
+
+    lines.join("\n") + "\n"
+}
+
+/// Prints statistics about the rules in the given file, as text or json
+pub fn stats(
+    rules_file_name: Option<&String>,
+    top: Option<&usize>,
+    json: bool,
+) -> Result<(), Error> {
+    let rules_file_name =
+        rules_file_name.ok_or(Error::new(InvalidInput, "No rules file was given"))?;
+
+    let rules = read_rules(rules_file_name)?;
+    let stats = get_rules_stats(&rules, *top.unwrap_or(&10));
+
+    match json {
+        true => println!("{}", serde_json::to_string(&stats)?),
+        false => print!("{}", format_stats(&stats)),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,59 @@
+use crate::wfc::wfc::classes::ClassMap;
+
+/// Lines starting with this are ignored
+static COMMENT: &str = "#";
+
+/// Parses a class map with a word and its class on every line, separated by whitespace, like
+/// "cat NOUN". Words are lowercased like the text tokens if [lowercase] is set, empty lines and
+/// comments are ignored.
+pub fn parse_class_map(text: &str, lowercase: bool) -> Result<ClassMap, &'static str> {
+    let mut classes = ClassMap::new();
+
+    for line in text.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with(COMMENT) {
+            continue;
+        }
+        let mut fields = line.split_whitespace();
+        let (word, class) = match (fields.next(), fields.next(), fields.next()) {
+            (Some(word), Some(class), None) => (word, class),
+            (_, None, _) => return Err("A word of the class map has no class"),
+            _ => return Err("A line of the class map has more than a word and a class"),
+        };
+        let word = match lowercase {
+            true => word.to_lowercase(),
+            false => word.to_string(),
+        };
+        classes.insert(word, class.to_string());
+    }
+
+    Ok(classes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_class_map;
+
+    #[test]
+    fn test_parse_class_map_sanity() {
+        let text = "# animals\nCat NOUN\n\n  dog\tNOUN\nsat VERB\n";
+        let classes = parse_class_map(text, true).unwrap();
+
+        assert_eq!(classes.len(), 3);
+        assert_eq!(classes["cat"], "NOUN");
+        assert_eq!(classes["dog"], "NOUN");
+        assert_eq!(classes["sat"], "VERB");
+
+        let classes = parse_class_map(text, false).unwrap();
+        assert_eq!(classes["Cat"], "NOUN");
+        assert!(!classes.contains_key("cat"));
+
+        assert_eq!(
+            parse_class_map("cat", true),
+            Err("A word of the class map has no class")
+        );
+        assert_eq!(
+            parse_class_map("cat NOUN VERB", true),
+            Err("A line of the class map has more than a word and a class")
+        );
+    }
+}
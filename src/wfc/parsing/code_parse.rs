@@ -0,0 +1,334 @@
+use crate::wfc::wfc::rules::{Rules, END, START};
+
+use super::text_parse::convert_tokens_to_rules;
+
+/// The token representing whitespace between two tokens on the same line
+pub static SPACE: &str = " ";
+/// Operators made of more than one char, longest first so they are matched greedily
+static OPERATORS: &[&str] = &[
+    "<<=", ">>=", "...", "..=", "**=", "//=", "::", "->", "=>", "==", "!=", "<=", ">=", "&&", "||",
+    "+=", "-=", "*=", "/=", "%=", "^=", "&=", "|=", "<<", ">>", "..", "**", "//",
+];
+
+/// The language of the source code, which decides how comments and string literals are read
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Language {
+    /// Rust, and languages with the same comments and quotes such as C or JavaScript
+    Rust,
+    Python,
+}
+
+/// Returns wether the char can be part of an identifier
+fn is_identifier_char(char: char) -> bool {
+    char.is_alphanumeric() || char == '_'
+}
+
+/// Returns the index of the first occurrence of the chars from the index, or the end of the chars
+fn find(chars: &[char], index: usize, pattern: &[char]) -> usize {
+    (index..chars.len())
+        .find(|i| chars[*i..].starts_with(pattern))
+        .unwrap_or(chars.len())
+}
+
+/// Returns the index right after the comment starting at the index, if there is one. Line comments
+/// last until the end of the line, block comments until they are closed
+fn get_comment_end(chars: &[char], index: usize, language: Language) -> Option<usize> {
+    match (language, chars[index], chars.get(index + 1)) {
+        (Language::Rust, '/', Some('/')) => Some(find(chars, index, &['\n'])),
+        (Language::Rust, '/', Some('*')) => {
+            Some((find(chars, index + 2, &['*', '/']) + 2).min(chars.len()))
+        }
+        (Language::Python, '#', _) => Some(find(chars, index, &['\n'])),
+        _ => None,
+    }
+}
+
+/// Returns the index right after the string literal starting at the index, if there is one.
+/// Rust strings can span several lines, Python strings only when their quotes are tripled.
+fn get_literal_end(chars: &[char], index: usize, language: Language) -> Option<usize> {
+    let quote = chars[index];
+    let is_triple = language == Language::Python && chars[index..].starts_with(&[quote; 3]);
+    let (mut i, limit) = match (language, quote) {
+        // A single quote with a long content is a rust lifetime and not a char literal
+        (Language::Rust, '\'') => (index + 1, (index + 4).min(chars.len())),
+        (Language::Rust, _) => (index + 1, chars.len()),
+        (Language::Python, _) if is_triple => (index + 3, chars.len()),
+        (Language::Python, _) => (index + 1, find(chars, index, &['\n'])),
+    };
+
+    while i < limit {
+        match chars[i] {
+            '\\' => i += 2,
+            _ if is_triple && chars[i..].starts_with(&[quote; 3]) => return Some(i + 3),
+            c if c == quote && !is_triple => return Some(i + 1),
+            _ => i += 1,
+        }
+    }
+
+    // Only the literals which can span lines last until the end when they aren't closed
+    match (language, quote) {
+        (Language::Rust, '"') => Some(chars.len()),
+        (Language::Python, _) if is_triple => Some(chars.len()),
+        _ => None,
+    }
+}
+
+/// Returns the index right after the identifier or number starting at the index. A number only
+/// keeps a single `.` followed by a digit, so that ranges like `1..10` are split.
+fn get_identifier_end(chars: &[char], index: usize) -> usize {
+    let is_number = chars[index].is_ascii_digit();
+    let mut has_dot = false;
+    let mut i = index;
+
+    while i < chars.len() {
+        let is_decimal_dot = is_number
+            && !has_dot
+            && chars[i] == '.'
+            && chars.get(i + 1).is_some_and(char::is_ascii_digit);
+        if !is_identifier_char(chars[i]) && !is_decimal_dot {
+            break;
+        }
+        has_dot |= is_decimal_dot;
+        i += 1;
+    }
+
+    i
+}
+
+/// Returns the given source code as an array of tokens, keeping case, indentation and spacing.
+/// The whole text is read at once so that strings and comments spanning lines stay single tokens.
+fn parse_code(text: String, language: Language) -> Vec<String> {
+    let mut text = text.replace("\r\n", "\n");
+    if text.ends_with('\n') {
+        text.pop();
+    }
+
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = vec![START.to_string()];
+    let mut line_start = true;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let char = chars[i];
+        let start = i;
+
+        if char == '\n' {
+            tokens.push("\n".to_string());
+            line_start = true;
+            i += 1;
+            continue;
+        }
+
+        if char.is_whitespace() {
+            while i < chars.len() && chars[i].is_whitespace() && chars[i] != '\n' {
+                i += 1;
+            }
+            // Trailing whitespace and blank lines have no meaning
+            if i < chars.len() && chars[i] != '\n' {
+                tokens.push(match line_start {
+                    true => chars[start..i].iter().collect(),
+                    false => SPACE.to_string(),
+                });
+            }
+            line_start = false;
+            continue;
+        }
+        line_start = false;
+
+        if let Some(end) = get_comment_end(&chars, i, language) {
+            i = end;
+        } else if is_identifier_char(char) {
+            i = get_identifier_end(&chars, i);
+        } else if char == '"' || char == '\'' {
+            i = get_literal_end(&chars, i, language).unwrap_or(i + 1);
+        } else {
+            let rest: String = chars[i..].iter().take(3).collect();
+            i += OPERATORS
+                .iter()
+                .find(|operator| rest.starts_with(*operator))
+                .map_or(1, |operator| operator.chars().count());
+        }
+
+        tokens.push(chars[start..i].iter().collect());
+    }
+
+    tokens.push(END.to_string());
+    tokens
+}
+
+/// Generates rules from existing source code in the language
+pub fn generate_code_rules(text: String, language: Language) -> Rules {
+    let tokens = parse_code(text, language);
+    convert_tokens_to_rules(&tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::wfc::wfc::rules::{END, START};
+
+    use super::{generate_code_rules, parse_code, Language};
+
+    #[test]
+    fn test_parse_code_sanity() {
+        let text = "fn main() {\n    let x_1 = 1.5;\n}\n".to_string();
+        assert_eq!(
+            parse_code(text, Language::Rust),
+            [
+                START, "fn", " ", "main", "(", ")", " ", "{", "\n", "    ", "let", " ", "x_1", " ",
+                "=", " ", "1.5", ";", "\n", "}", END
+            ]
+            .map(|s| s.to_string())
+            .into_iter()
+            .collect::<Vec<_>>()
+        )
+    }
+
+    #[test]
+    fn test_parse_code_operators_and_literals() {
+        let text = "a::b->c != \"x, y\" && '\\n' // comment".to_string();
+        assert_eq!(
+            parse_code(text, Language::Rust),
+            [
+                START,
+                "a",
+                "::",
+                "b",
+                "->",
+                "c",
+                " ",
+                "!=",
+                " ",
+                "\"x, y\"",
+                " ",
+                "&&",
+                " ",
+                "'\\n'",
+                " ",
+                "// comment",
+                END
+            ]
+            .map(|s| s.to_string())
+            .into_iter()
+            .collect::<Vec<_>>()
+        )
+    }
+
+    #[test]
+    fn test_parse_code_rust_attributes() {
+        let text = "#[derive(Clone)]\nfn f<'a>() -> char { 'x' }".to_string();
+        assert_eq!(
+            parse_code(text, Language::Rust),
+            [
+                START, "#", "[", "derive", "(", "Clone", ")", "]", "\n", "fn", " ", "f", "<", "'",
+                "a", ">", "(", ")", " ", "->", " ", "char", " ", "{", " ", "'x'", " ", "}", END
+            ]
+            .map(|s| s.to_string())
+            .into_iter()
+            .collect::<Vec<_>>()
+        )
+    }
+
+    #[test]
+    fn test_parse_code_python() {
+        let text = "def f(a, b):\n\treturn a // b  # done".to_string();
+        assert_eq!(
+            parse_code(text, Language::Python),
+            [
+                START, "def", " ", "f", "(", "a", ",", " ", "b", ")", ":", "\n", "\t", "return",
+                " ", "a", " ", "//", " ", "b", " ", "# done", END
+            ]
+            .map(|s| s.to_string())
+            .into_iter()
+            .collect::<Vec<_>>()
+        )
+    }
+
+    #[test]
+    fn test_parse_code_python_strings() {
+        let text = "x = 'hello world' + \"it's\"\n'''a\nb''' 'c\nd'".to_string();
+        assert_eq!(
+            parse_code(text, Language::Python),
+            [
+                START,
+                "x",
+                " ",
+                "=",
+                " ",
+                "'hello world'",
+                " ",
+                "+",
+                " ",
+                "\"it's\"",
+                "\n",
+                "'''a\nb'''",
+                " ",
+                "'",
+                "c",
+                "\n",
+                "d",
+                "'",
+                END
+            ]
+            .map(|s| s.to_string())
+            .into_iter()
+            .collect::<Vec<_>>()
+        )
+    }
+
+    #[test]
+    fn test_parse_code_multiline_literals() {
+        let text = "x = \"a\nb\"\n/* c\n d */ y".to_string();
+        assert_eq!(
+            parse_code(text, Language::Rust),
+            [
+                START,
+                "x",
+                " ",
+                "=",
+                " ",
+                "\"a\nb\"",
+                "\n",
+                "/* c\n d */",
+                " ",
+                "y",
+                END
+            ]
+            .map(|s| s.to_string())
+            .into_iter()
+            .collect::<Vec<_>>()
+        )
+    }
+
+    #[test]
+    fn test_parse_code_comment_without_space() {
+        let text = "#!/usr/bin/env python\n#comment".to_string();
+        assert_eq!(
+            parse_code(text, Language::Python),
+            [START, "#!/usr/bin/env python", "\n", "#comment", END]
+                .map(|s| s.to_string())
+                .into_iter()
+                .collect::<Vec<_>>()
+        )
+    }
+
+    #[test]
+    fn test_parse_code_ranges() {
+        let text = "1..10 1.5.max(x)".to_string();
+        assert_eq!(
+            parse_code(text, Language::Rust),
+            [START, "1", "..", "10", " ", "1.5", ".", "max", "(", "x", ")", END]
+                .map(|s| s.to_string())
+                .into_iter()
+                .collect::<Vec<_>>()
+        )
+    }
+
+    #[test]
+    fn test_generate_code_rules_keeps_case() {
+        let rules = generate_code_rules("Foo foo".to_string(), Language::Rust);
+
+        assert!(rules.contains_key("Foo"));
+        assert!(rules.contains_key("foo"));
+        assert!(rules["Foo"].after.contains(" "));
+    }
+}
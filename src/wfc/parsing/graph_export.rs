@@ -0,0 +1,268 @@
+use serde::Serialize;
+use std::collections::HashSet;
+
+use crate::wfc::wfc::rules::{Rules, END, START};
+
+/// A transition between two words in the exported graph
+#[derive(Serialize)]
+struct Edge<'a> {
+    from: &'a str,
+    to: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    count: Option<usize>,
+}
+
+/// The exported graph, as serialized to json
+#[derive(Serialize)]
+struct Graph<'a> {
+    nodes: Vec<&'a str>,
+    edges: Vec<Edge<'a>>,
+}
+
+/// Returns the words at most [hops] transitions away from the word, in any direction
+pub fn get_neighborhood(rules: &Rules, word: &str, hops: usize) -> HashSet<String> {
+    let mut result: HashSet<String> = [word.to_string()].into_iter().collect();
+    let mut frontier = result.clone();
+
+    for _ in 0..hops {
+        let mut next = HashSet::<String>::new();
+        for word in &frontier {
+            if let Some(allowed) = rules.get(word) {
+                next.extend(allowed.before.iter().chain(allowed.after.iter()).cloned());
+            }
+        }
+        next.retain(|word| !result.contains(word));
+        result.extend(next.iter().cloned());
+        frontier = next;
+    }
+
+    result
+}
+
+/// Returns the graph of the rules, only with the given words if there are any
+fn get_graph<'a>(
+    rules: &'a Rules,
+    words: Option<&HashSet<String>>,
+    with_counts: bool,
+) -> Graph<'a> {
+    let mut nodes: Vec<&str> = rules
+        .keys()
+        .filter(|word| words.is_none_or(|words| words.contains(*word)))
+        .map(String::as_str)
+        .collect();
+    nodes.sort();
+    let node_set: HashSet<&str> = nodes.iter().copied().collect();
+
+    let mut edges = Vec::<Edge>::new();
+    for from in &nodes {
+        let allowed = &rules[*from];
+        let mut after: Vec<&str> = allowed
+            .after
+            .iter()
+            .map(String::as_str)
+            .filter(|to| node_set.contains(to))
+            .collect();
+        after.sort();
+
+        for to in after {
+            let count = match with_counts {
+                true => allowed.counts.get(to).copied(),
+                false => None,
+            };
+            edges.push(Edge { from, to, count });
+        }
+    }
+
+    Graph { nodes, edges }
+}
+
+impl Graph<'_> {
+    /// Returns the index of the word in the sorted nodes, used as its id
+    fn get_id(&self, word: &str) -> usize {
+        self.nodes.binary_search(&word).unwrap_or_default()
+    }
+}
+
+/// Returns a readable name for the word, naming the invisible tokens
+fn get_label(word: &str) -> String {
+    match word {
+        word if word == START => "<start>".to_string(),
+        word if word == END => "<end>".to_string(),
+        "\n" => "<newline>".to_string(),
+        word => word.to_string(),
+    }
+}
+
+/// Escapes the string for a quoted dot string
+fn escape_dot(string: &str) -> String {
+    string.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Escapes the string for an xml attribute or text
+fn escape_xml(string: &str) -> String {
+    string
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// Returns the rules as a Graphviz dot digraph, with an edge for every allowed transition. Nodes
+/// are identified by their index and named by their label, so that a word looking like the label of
+/// an invisible token stays a separate node
+pub fn rules_to_dot(rules: &Rules, words: Option<&HashSet<String>>, with_counts: bool) -> String {
+    let graph = get_graph(rules, words, with_counts);
+    let mut lines = vec!["digraph rules {".to_string()];
+
+    for (i, node) in graph.nodes.iter().enumerate() {
+        let label = escape_dot(&get_label(node));
+        lines.push(format!("    n{i} [label=\"{label}\"];"));
+    }
+    for edge in &graph.edges {
+        let (from, to) = (graph.get_id(edge.from), graph.get_id(edge.to));
+        match edge.count {
+            Some(count) => lines.push(format!("    n{from} -> n{to} [label=\"{count}\"];")),
+            None => lines.push(format!("    n{from} -> n{to};")),
+        }
+    }
+
+    lines.push("}".to_string());
+    lines.join("\n") + "\n"
+}
+
+/// Returns the rules as a GraphML document, with an edge for every allowed transition
+pub fn rules_to_graphml(
+    rules: &Rules,
+    words: Option<&HashSet<String>>,
+    with_counts: bool,
+) -> String {
+    let graph = get_graph(rules, words, with_counts);
+    let mut lines = vec![
+        r#"<?xml version="1.0" encoding="UTF-8"?>"#.to_string(),
+        r#"<graphml xmlns="http://graphml.graphdrawing.org/xmlns">"#.to_string(),
+        r#"  <key id="label" for="node" attr.name="label" attr.type="string"/>"#.to_string(),
+        r#"  <key id="count" for="edge" attr.name="count" attr.type="int"/>"#.to_string(),
+        r#"  <graph id="rules" edgedefault="directed">"#.to_string(),
+    ];
+
+    for (i, node) in graph.nodes.iter().enumerate() {
+        let label = escape_xml(&get_label(node));
+        lines.push(format!(
+            r#"    <node id="n{i}"><data key="label">{label}</data></node>"#
+        ));
+    }
+
+    for edge in &graph.edges {
+        let (from, to) = (graph.get_id(edge.from), graph.get_id(edge.to));
+        match edge.count {
+            Some(count) => lines.push(format!(
+                r#"    <edge source="n{from}" target="n{to}"><data key="count">{count}</data></edge>"#
+            )),
+            None => lines.push(format!(r#"    <edge source="n{from}" target="n{to}"/>"#)),
+        }
+    }
+
+    lines.push("  </graph>".to_string());
+    lines.push("</graphml>".to_string());
+    lines.join("\n") + "\n"
+}
+
+/// Returns the rules as a json object with a list of nodes and a list of edges
+pub fn rules_to_graph_json(
+    rules: &Rules,
+    words: Option<&HashSet<String>>,
+    with_counts: bool,
+) -> Result<String, serde_json::Error> {
+    serde_json::to_string(&get_graph(rules, words, with_counts))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::wfc::wfc::rules::{add_transition, Rules, END, START};
+
+    use super::{get_neighborhood, rules_to_dot, rules_to_graph_json, rules_to_graphml};
+
+    fn get_rules() -> Rules {
+        let mut rules = Rules::new();
+        let transitions = [
+            (START, "the", 2),
+            ("the", "cat", 1),
+            ("the", "\"dog\"", 1),
+            ("cat", END, 1),
+            ("\"dog\"", END, 1),
+        ];
+        for (before, after, count) in transitions {
+            add_transition(&mut rules, before, after, count);
+        }
+        rules
+    }
+
+    #[test]
+    fn test_get_neighborhood_sanity() {
+        let rules = get_rules();
+
+        assert_eq!(get_neighborhood(&rules, "cat", 0).len(), 1);
+        let neighborhood = get_neighborhood(&rules, "cat", 1);
+        assert_eq!(neighborhood.len(), 3);
+        assert!(neighborhood.contains("the"));
+        assert!(neighborhood.contains(END));
+        assert_eq!(get_neighborhood(&rules, "cat", 2).len(), 5);
+    }
+
+    #[test]
+    fn test_rules_to_dot_sanity() {
+        let rules = get_rules();
+        let words = get_neighborhood(&rules, "cat", 1);
+
+        assert_eq!(
+            rules_to_dot(&rules, Some(&words), true),
+            [
+                "digraph rules {",
+                "    n0 [label=\"<end>\"];",
+                "    n1 [label=\"cat\"];",
+                "    n2 [label=\"the\"];",
+                "    n1 -> n0 [label=\"1\"];",
+                "    n2 -> n1 [label=\"1\"];",
+                "}\n",
+            ]
+            .join("\n")
+        );
+        let dot = rules_to_dot(&rules, None, false);
+        assert!(dot.contains("    n2 [label=\"\\\"dog\\\"\"];"));
+        assert!(dot.contains("    n4 -> n2;"));
+    }
+
+    #[test]
+    fn test_rules_to_dot_label_collision() {
+        let mut rules = get_rules();
+        add_transition(&mut rules, "the", "<end>", 1);
+        add_transition(&mut rules, "<end>", END, 1);
+
+        let dot = rules_to_dot(&rules, None, false);
+
+        assert_eq!(dot.matches("[label=\"<end>\"]").count(), 2);
+        assert_eq!(dot.matches(" -> ").count(), 7);
+    }
+
+    #[test]
+    fn test_rules_to_graphml_sanity() {
+        let rules = get_rules();
+        let graphml = rules_to_graphml(&rules, None, false);
+
+        assert!(graphml.contains(r#"<node id="n0"><data key="label">&lt;start&gt;</data></node>"#));
+        assert!(graphml.contains(r#"<data key="label">&quot;dog&quot;</data>"#));
+        assert_eq!(graphml.matches("<edge ").count(), 5);
+    }
+
+    #[test]
+    fn test_rules_to_graph_json_sanity() {
+        let rules = get_rules();
+        let words = get_neighborhood(&rules, START, 1);
+
+        assert_eq!(
+            rules_to_graph_json(&rules, Some(&words), true).unwrap(),
+            r#"{"nodes":["\u0002","the"],"edges":[{"from":"\u0002","to":"the","count":2}]}"#
+        );
+    }
+}
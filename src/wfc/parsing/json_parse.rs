@@ -1,3 +1,5 @@
+use crate::wfc::wfc::classes::Members;
+use crate::wfc::wfc::constraints::Constraints;
 use crate::wfc::wfc::rules::Rules;
 
 /// Parses a json string as a rules type type
@@ -10,12 +12,28 @@ pub fn rules_to_json(rules: &Rules) -> Result<String, serde_json::Error> {
     serde_json::to_string(rules)
 }
 
+/// Parses a json string as the words of every class
+pub fn parse_members_json(json_string: &str) -> Result<Members, serde_json::Error> {
+    serde_json::from_str(json_string)
+}
+
+/// Returns a string object of the serialized words of every class
+pub fn members_to_json(members: &Members) -> Result<String, serde_json::Error> {
+    serde_json::to_string(members)
+}
+
+/// Parses a json string as a constraints type, missing fields are left unconstrained
+pub fn parse_constraints_json(json_string: &str) -> Result<Constraints, serde_json::Error> {
+    serde_json::from_str(json_string)
+}
+
 #[cfg(test)]
+#[allow(clippy::unnecessary_to_owned)]
 mod tests {
 
     use crate::wfc::wfc::rules::{Allowed, Rules, END, START};
 
-    use super::{parse_rules_json, rules_to_json};
+    use super::{parse_constraints_json, parse_rules_json, rules_to_json};
 
     #[test]
     fn test_parse_rules_json_sanity() {
@@ -86,4 +104,22 @@ mod tests {
         assert_eq!(result["hello"].after.len(), 2);
         assert!(result["hello"].before.contains(&START.to_string()));
     }
+
+    #[test]
+    fn test_parse_constraints_json_sanity() {
+        let string = r#"
+            {
+                "banned": ["bad", "worse"],
+                "max_occurrences": 3
+            }
+        "#;
+
+        let result = parse_constraints_json(string).unwrap();
+
+        assert_eq!(result.banned.len(), 2);
+        assert!(result.banned.contains("bad"));
+        assert!(result.required.is_empty());
+        assert_eq!(result.max_occurrences, Some(3));
+        assert_eq!(result.no_repeat_ngram, None);
+    }
 }
@@ -0,0 +1,252 @@
+use std::collections::HashMap;
+use std::io::{Error, ErrorKind::InvalidData};
+
+use midly::{MidiMessage, Smf, Timing, TrackEventKind};
+
+use crate::wfc::wfc::rules::{Rules, END, START};
+
+use super::text_parse::convert_tokens_to_rules;
+
+/// The resolution all durations are normalized to (ticks per quarter note)
+pub static TICKS_PER_BEAT: u16 = 480;
+/// The smallest duration kept in a token (a 32nd note), everything is rounded to it
+pub static TICKS_QUANTUM: u32 = 60;
+/// The pitch part of a rest token
+pub static REST: &str = "r";
+
+/// A note found in the midi file, with absolute times in normalized ticks
+struct Note {
+    start: u32,
+    pitch: u8,
+    duration: u32,
+}
+
+/// Returns the token of a note (or a rest if the pitch is empty) lasting the given ticks
+pub fn note_token(pitch: Option<u8>, duration: u32) -> String {
+    match pitch {
+        Some(pitch) => format!("{pitch}:{duration}"),
+        None => format!("{REST}:{duration}"),
+    }
+}
+
+/// Parses a note token back to its pitch (empty for rests) and duration
+pub fn parse_note_token(token: &str) -> Option<(Option<u8>, u32)> {
+    let (pitch, duration) = token.split_once(':')?;
+    let duration = duration.parse::<u32>().ok()?;
+    match pitch == REST {
+        true => Some((None, duration)),
+        false => Some((Some(pitch.parse::<u8>().ok()?), duration)),
+    }
+}
+
+/// Rounds the ticks to the nearest quantum
+fn quantize(ticks: u32) -> u32 {
+    (ticks + TICKS_QUANTUM / 2) / TICKS_QUANTUM * TICKS_QUANTUM
+}
+
+/// Returns every note of every track, sorted by start time and then from the highest pitch
+fn collect_notes(smf: &Smf) -> Result<Vec<Note>, Error> {
+    let ticks_per_beat = match smf.header.timing {
+        Timing::Metrical(ticks) if ticks.as_int() == 0 => {
+            return Err(Error::new(InvalidData, "The file has no ticks per beat"))
+        }
+        Timing::Metrical(ticks) => ticks.as_int() as u64,
+        Timing::Timecode(_, _) => TICKS_PER_BEAT as u64,
+    };
+    let normalize = |ticks: u64| (ticks * TICKS_PER_BEAT as u64 / ticks_per_beat) as u32;
+
+    let mut notes = Vec::<Note>::new();
+    for track in &smf.tracks {
+        let mut time = 0u64;
+        let mut playing = HashMap::<(u8, u8), u64>::new();
+
+        for event in track {
+            time += event.delta.as_int() as u64;
+            if let TrackEventKind::Midi { channel, message } = event.kind {
+                let (key, is_on) = match message {
+                    MidiMessage::NoteOn { key, vel } => (key.as_int(), vel.as_int() > 0),
+                    MidiMessage::NoteOff { key, .. } => (key.as_int(), false),
+                    _ => continue,
+                };
+
+                let id = (channel.as_int(), key);
+                if is_on {
+                    playing.entry(id).or_insert(time);
+                } else if let Some(start) = playing.remove(&id) {
+                    notes.push(Note {
+                        start: quantize(normalize(start)),
+                        pitch: key,
+                        duration: quantize(normalize(time - start)).max(TICKS_QUANTUM),
+                    });
+                }
+            }
+        }
+    }
+
+    notes.sort_by(|a, b| a.start.cmp(&b.start).then(b.pitch.cmp(&a.pitch)));
+    Ok(notes)
+}
+
+/// Returns the notes of the midi file as an array of tokens, keeping only the highest note of chords
+fn parse_midi(bytes: &[u8]) -> Result<Vec<String>, Error> {
+    let smf = Smf::parse(bytes).map_err(|e| Error::new(InvalidData, e))?;
+    let notes = collect_notes(&smf)?;
+    let mut melody = Vec::<&Note>::new();
+
+    for note in &notes {
+        if melody.last().is_none_or(|last| last.start < note.start) {
+            melody.push(note);
+        }
+    }
+
+    let mut tokens = vec![START.to_string()];
+    for (i, note) in melody.iter().enumerate() {
+        let next_start = melody.get(i + 1).map(|next| next.start);
+        let duration = match next_start {
+            Some(next_start) => note.duration.min(next_start - note.start),
+            None => note.duration,
+        };
+        tokens.push(note_token(Some(note.pitch), duration));
+
+        if let Some(next_start) = next_start {
+            let rest = next_start - note.start - duration;
+            if rest > 0 {
+                tokens.push(note_token(None, rest));
+            }
+        }
+    }
+    tokens.push(END.to_string());
+
+    Ok(tokens)
+}
+
+/// Generates rules from the melody of an existing midi file
+pub fn generate_midi_rules(bytes: &[u8]) -> Result<Rules, Error> {
+    let tokens = parse_midi(bytes)?;
+    Ok(convert_tokens_to_rules(&tokens))
+}
+
+#[cfg(test)]
+mod tests {
+    use midly::{
+        Format, Header, MetaMessage, MidiMessage, Smf, Timing, TrackEvent, TrackEventKind,
+    };
+
+    use std::io::ErrorKind::InvalidData;
+
+    use crate::wfc::wfc::rules::{END, START};
+
+    use super::{generate_midi_rules, note_token, parse_midi, parse_note_token};
+
+    fn note_event(delta: u32, key: u8, vel: u8) -> TrackEvent<'static> {
+        TrackEvent {
+            delta: delta.into(),
+            kind: TrackEventKind::Midi {
+                channel: 0.into(),
+                message: MidiMessage::NoteOn {
+                    key: key.into(),
+                    vel: vel.into(),
+                },
+            },
+        }
+    }
+
+    fn get_midi(ticks_per_beat: u16, events: Vec<TrackEvent<'static>>) -> Vec<u8> {
+        let mut smf = Smf::new(Header::new(
+            Format::SingleTrack,
+            Timing::Metrical(ticks_per_beat.into()),
+        ));
+        let mut track = events;
+        track.push(TrackEvent {
+            delta: 0.into(),
+            kind: TrackEventKind::Meta(MetaMessage::EndOfTrack),
+        });
+        smf.tracks.push(track);
+
+        let mut bytes = Vec::<u8>::new();
+        smf.write_std(&mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn test_note_token_sanity() {
+        assert_eq!(note_token(Some(60), 480), "60:480");
+        assert_eq!(note_token(None, 240), "r:240");
+        assert_eq!(parse_note_token("60:480"), Some((Some(60), 480)));
+        assert_eq!(parse_note_token("r:240"), Some((None, 240)));
+        assert_eq!(parse_note_token("hello"), None);
+    }
+
+    #[test]
+    fn test_parse_midi_sanity() {
+        let bytes = get_midi(
+            480,
+            vec![
+                note_event(0, 60, 64),
+                note_event(480, 60, 0),
+                note_event(240, 62, 64),
+                note_event(240, 62, 0),
+            ],
+        );
+
+        assert_eq!(
+            parse_midi(&bytes).unwrap(),
+            [START, "60:480", "r:240", "62:240", END]
+                .map(|s| s.to_string())
+                .into_iter()
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_parse_midi_chords_and_resolution() {
+        let bytes = get_midi(
+            96,
+            vec![
+                note_event(0, 60, 64),
+                note_event(0, 64, 64),
+                note_event(96, 60, 0),
+                note_event(0, 64, 0),
+                note_event(0, 67, 64),
+                note_event(48, 67, 0),
+            ],
+        );
+
+        assert_eq!(
+            parse_midi(&bytes).unwrap(),
+            [START, "64:480", "67:240", END]
+                .map(|s| s.to_string())
+                .into_iter()
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_parse_midi_invalid() {
+        assert!(parse_midi(b"hello world").is_err());
+
+        let bytes = get_midi(0, vec![note_event(0, 60, 64), note_event(480, 60, 0)]);
+        assert_eq!(parse_midi(&bytes).unwrap_err().kind(), InvalidData);
+    }
+
+    #[test]
+    fn test_generate_midi_rules_sanity() {
+        let bytes = get_midi(
+            480,
+            vec![
+                note_event(0, 60, 64),
+                note_event(480, 60, 0),
+                note_event(0, 62, 64),
+                note_event(480, 62, 0),
+                note_event(0, 60, 64),
+                note_event(480, 60, 0),
+            ],
+        );
+        let rules = generate_midi_rules(&bytes).unwrap();
+
+        assert_eq!(rules.keys().len(), 4);
+        assert!(rules["60:480"].after.contains("62:480"));
+        assert!(rules["60:480"].after.contains(END));
+        assert!(rules["62:480"].before.contains("60:480"));
+    }
+}
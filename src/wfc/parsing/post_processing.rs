@@ -1,5 +1,13 @@
+use midly::{Format, Header, MetaMessage, MidiMessage, Smf, Timing, TrackEvent, TrackEventKind};
+
 use crate::wfc::wfc::rules::{END, START};
 
+use super::code_parse::SPACE;
+use super::midi_parse::{parse_note_token, TICKS_PER_BEAT};
+
+/// The velocity of the notes written to generated midi files
+static MIDI_VELOCITY: u8 = 64;
+
 pub static BEFORE_TOKENS: &[char] = &['(', '\n', START.as_bytes()[0] as char];
 pub static AFTER_TOKENS: &[char] = &[
     '.',
@@ -29,12 +37,12 @@ pub fn remove_double_char(mut string: String, char: char) -> String {
 fn fix_tokens(mut string: String) -> String {
     for token in BEFORE_TOKENS {
         let before_string = format!("{} ", &token.to_string().as_str());
-        string = string.replace(&before_string, &token.to_string().as_str());
+        string = string.replace(&before_string, token.to_string().as_str());
     }
 
     for token in AFTER_TOKENS {
         let after_string = format!(" {}", &token.to_string().as_str());
-        string = string.replace(&after_string, &token.to_string().as_str());
+        string = string.replace(&after_string, token.to_string().as_str());
     }
 
     string
@@ -57,10 +65,87 @@ pub fn merge(vector: Vec<String>) -> String {
     result
 }
 
+/// Merges a vector of code tokens into source code, keeping indentation only at the start of lines
+pub fn merge_code(vector: Vec<String>) -> String {
+    let mut result = String::new();
+    let mut line_start = true;
+
+    for token in vector {
+        if token == START {
+            continue;
+        }
+        if token == END || token == "\n" {
+            result.truncate(result.trim_end_matches([' ', '\t']).len());
+            result.push('\n');
+            line_start = true;
+            continue;
+        }
+
+        let is_whitespace = token.chars().all(char::is_whitespace);
+        if is_whitespace && !line_start {
+            // Indentation out of place is only a separator
+            if !result.ends_with([' ', '\t']) {
+                result.push_str(SPACE);
+            }
+        } else {
+            result.push_str(&token);
+            line_start = line_start && is_whitespace;
+        }
+    }
+
+    result
+}
+
+/// Writes the vector of note tokens as a single track midi file, ignoring tokens which are not notes
+pub fn merge_midi(vector: Vec<String>) -> Vec<u8> {
+    let mut track = Vec::<TrackEvent>::new();
+    let mut delta = 0u32;
+
+    for (pitch, duration) in vector.iter().filter_map(|token| parse_note_token(token)) {
+        match pitch {
+            Some(pitch) => {
+                for (event_delta, vel) in [(delta, MIDI_VELOCITY), (duration, 0)] {
+                    track.push(TrackEvent {
+                        delta: event_delta.into(),
+                        kind: TrackEventKind::Midi {
+                            channel: 0.into(),
+                            message: MidiMessage::NoteOn {
+                                key: pitch.into(),
+                                vel: vel.into(),
+                            },
+                        },
+                    });
+                }
+                delta = 0;
+            }
+            None => delta += duration,
+        }
+    }
+
+    track.push(TrackEvent {
+        delta: delta.into(),
+        kind: TrackEventKind::Meta(MetaMessage::EndOfTrack),
+    });
+
+    let mut smf = Smf::new(Header::new(
+        Format::SingleTrack,
+        Timing::Metrical(TICKS_PER_BEAT.into()),
+    ));
+    smf.tracks.push(track);
+
+    let mut bytes = Vec::<u8>::new();
+    smf.write_std(&mut bytes)
+        .expect("Writing to a vector can't fail");
+    bytes
+}
+
 #[cfg(test)]
 mod tests {
     use crate::wfc::{
-        parsing::post_processing::merge,
+        parsing::{
+            midi_parse::generate_midi_rules,
+            post_processing::{merge, merge_code, merge_midi},
+        },
         wfc::rules::{END, START},
     };
 
@@ -134,4 +219,49 @@ mod tests {
             "hello world!\nis this (thing), working?\n".to_string()
         );
     }
+
+    #[test]
+    fn test_merge_midi_sanity() {
+        let vector = [START, "60:480", "r:240", "62:240", "", END]
+            .map(|s| s.to_string())
+            .into_iter()
+            .collect::<Vec<_>>();
+
+        let bytes = merge_midi(vector);
+        let rules = generate_midi_rules(&bytes).unwrap();
+
+        assert_eq!(rules.keys().len(), 5);
+        assert!(rules[START].after.contains("60:480"));
+        assert!(rules["60:480"].after.contains("r:240"));
+        assert!(rules["r:240"].after.contains("62:240"));
+        assert!(rules["62:240"].after.contains(END));
+    }
+
+    #[test]
+    fn test_merge_code_sanity() {
+        let vector = [
+            START, "fn", " ", "main", "(", ")", " ", "{", "\n", "    ", "let", " ", "x", " ", "=",
+            " ", "1", ";", "\n", "}", END,
+        ]
+        .map(|s| s.to_string())
+        .into_iter()
+        .collect::<Vec<_>>();
+
+        assert_eq!(
+            merge_code(vector),
+            "fn main() {\n    let x = 1;\n}\n".to_string()
+        );
+    }
+
+    #[test]
+    fn test_merge_code_reindents() {
+        let vector = [
+            START, "    ", "x", "    ", "=", " ", "\t", "1", " ", "\n", " ", "\n", "y", "", END,
+        ]
+        .map(|s| s.to_string())
+        .into_iter()
+        .collect::<Vec<_>>();
+
+        assert_eq!(merge_code(vector), "    x = 1\n\ny\n".to_string());
+    }
 }
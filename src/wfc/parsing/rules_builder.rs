@@ -0,0 +1,248 @@
+use std::io::{Error, ErrorKind, Read};
+use std::str::from_utf8;
+
+use crate::wfc::wfc::rules::{add_transition, Rules, END, START};
+
+use super::text_parse::TextTokenizer;
+
+/// How many bytes [RulesBuilder::feed_reader] reads at a time
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Builds rules from text fed a chunk at a time, only keeping the rules and the current word in
+/// memory. Produces the same rules as [super::text_parse::generate_rules] on the whole text.
+pub struct RulesBuilder {
+    rules: Rules,
+    tokenizer: TextTokenizer,
+    /// The last token of the current document
+    previous: String,
+    /// Wether anything was fed since the current document started
+    started: bool,
+}
+
+impl Default for RulesBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RulesBuilder {
+    /// Creates a builder with no rules
+    pub fn new() -> Self {
+        Self {
+            rules: Rules::new(),
+            tokenizer: TextTokenizer::default(),
+            previous: START.to_string(),
+            started: false,
+        }
+    }
+
+    /// Feeds the next chunk of the current document
+    pub fn feed(&mut self, text: &str) {
+        let (rules, previous) = (&mut self.rules, &mut self.previous);
+        self.tokenizer.feed(text, &mut |token| {
+            add_transition(rules, previous, &token, 1);
+            *previous = token;
+        });
+        self.started = true;
+    }
+
+    /// Feeds everything the reader has, a chunk of bytes at a time so that text without line breaks
+    /// isn't read whole. The bytes of a char cut at the end of a chunk are kept for the next one.
+    pub fn feed_reader(&mut self, mut reader: impl Read) -> Result<(), Error> {
+        let mut buffer = vec![0u8; CHUNK_SIZE];
+        // How many bytes of an unfinished char were kept from the previous chunk
+        let mut kept = 0;
+
+        loop {
+            let read = match reader.read(&mut buffer[kept..]) {
+                Ok(0) => break,
+                Ok(read) => read,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            let length = kept + read;
+
+            let valid = match from_utf8(&buffer[..length]) {
+                Ok(text) => text.len(),
+                Err(e) if e.error_len().is_none() => e.valid_up_to(),
+                Err(e) => return Err(Error::new(ErrorKind::InvalidData, e)),
+            };
+            if let Ok(text) = from_utf8(&buffer[..valid]) {
+                self.feed(text);
+            }
+
+            buffer.copy_within(valid..length, 0);
+            kept = length - valid;
+        }
+
+        match kept {
+            0 => Ok(()),
+            _ => Err(Error::new(
+                ErrorKind::InvalidData,
+                "The text ends in the middle of a char",
+            )),
+        }
+    }
+
+    /// Ends the current document, the next text fed starts a new one
+    pub fn finish_document(&mut self) {
+        let (rules, previous) = (&mut self.rules, &mut self.previous);
+        self.tokenizer.finish(&mut |token| {
+            add_transition(rules, previous, &token, 1);
+            *previous = token;
+        });
+        add_transition(&mut self.rules, &self.previous, END, 1);
+        add_transition(&mut self.rules, END, START, 0);
+
+        self.previous = START.to_string();
+        self.started = false;
+    }
+
+    /// Returns the rules of every document, ending the current one if anything was fed to it (or
+    /// if nothing was fed at all, like an empty text)
+    pub fn build(mut self) -> Rules {
+        if self.started || self.rules.is_empty() {
+            self.finish_document();
+        }
+        self.rules
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{ErrorKind::InvalidData, Read};
+
+    use crate::wfc::parsing::text_parse::generate_rules;
+    use crate::wfc::wfc::rules::{merge_rules, Rules, END, START};
+
+    use super::RulesBuilder;
+
+    static TEXT: &str = "Hello there, Nice to meet (you)!\nHello world.\n";
+
+    #[test]
+    fn test_rules_builder_sanity() {
+        let mut builder = RulesBuilder::new();
+        for chunk in [
+            "Hel",
+            "lo there,",
+            " Nice to meet (y",
+            "ou)!\n",
+            "\nHello",
+            " world.\n",
+        ] {
+            builder.feed(chunk);
+        }
+        let rules = builder.build();
+        let expected = generate_rules(TEXT.replace("!\n", "!\n\n"));
+
+        assert_eq!(rules.len(), expected.len());
+        for (word, allowed) in &expected {
+            assert_eq!(rules[word].before, allowed.before);
+            assert_eq!(rules[word].after, allowed.after);
+            assert_eq!(rules[word].counts, allowed.counts);
+        }
+    }
+
+    #[test]
+    fn test_rules_builder_feed_reader() {
+        let mut builder = RulesBuilder::new();
+        builder.feed_reader(TEXT.as_bytes()).unwrap();
+        let rules = builder.build();
+        let expected = generate_rules(TEXT.to_string());
+
+        assert_eq!(rules.len(), expected.len());
+        for (word, allowed) in &expected {
+            assert_eq!(rules[word].after, allowed.after);
+            assert_eq!(rules[word].counts, allowed.counts);
+        }
+        assert!(!rules[END].before.contains("\n"));
+    }
+
+    #[test]
+    fn test_rules_builder_empty() {
+        let rules = RulesBuilder::new().build();
+        let expected = generate_rules(String::new());
+
+        assert_eq!(rules.len(), expected.len());
+        for (word, allowed) in &expected {
+            assert_eq!(rules[word].before, allowed.before);
+            assert_eq!(rules[word].after, allowed.after);
+            assert_eq!(rules[word].counts, allowed.counts);
+        }
+        assert!(rules[START].after.contains(END));
+    }
+
+    /// A reader giving a single byte at a time, cutting every char of more than one byte
+    struct ByteReader<'a>(&'a [u8]);
+
+    impl Read for ByteReader<'_> {
+        fn read(&mut self, buffer: &mut [u8]) -> std::io::Result<usize> {
+            match (self.0.split_first(), buffer.first_mut()) {
+                (Some((byte, rest)), Some(first)) => {
+                    *first = *byte;
+                    self.0 = rest;
+                    Ok(1)
+                }
+                _ => Ok(0),
+            }
+        }
+    }
+
+    #[test]
+    fn test_rules_builder_feed_reader_chunks() {
+        let text = "Héllo wörld, ça va?\nHéllo là";
+        let mut builder = RulesBuilder::new();
+        builder.feed_reader(ByteReader(text.as_bytes())).unwrap();
+        let rules = builder.build();
+        let expected = generate_rules(text.to_string());
+
+        assert_eq!(rules.len(), expected.len());
+        for (word, allowed) in &expected {
+            assert_eq!(rules[word].after, allowed.after);
+        }
+
+        let mut builder = RulesBuilder::new();
+        let result = builder.feed_reader(&"wörld".as_bytes()[..2]);
+        assert_eq!(result.unwrap_err().kind(), InvalidData);
+        let result = builder.feed_reader(&b"hello \xff"[..]);
+        assert_eq!(result.unwrap_err().kind(), InvalidData);
+    }
+
+    #[test]
+    fn test_rules_builder_documents() {
+        let mut builder = RulesBuilder::new();
+        builder.feed("hello world\n");
+        builder.finish_document();
+        builder.feed("hello there");
+        let rules = builder.build();
+
+        assert_eq!(rules[START].counts["hello"], 2);
+        assert_eq!(rules["world"].counts[END], 1);
+        assert_eq!(rules["there"].counts[END], 1);
+        assert!(rules[END].after.contains(START));
+        assert!(!rules.contains_key("\n"));
+    }
+
+    #[test]
+    fn test_rules_builder_merge() {
+        let documents = ["hello world\n", "Hello there, world.", "(world) hello"];
+
+        let mut builder = RulesBuilder::new();
+        for document in documents {
+            builder.feed(document);
+            builder.finish_document();
+        }
+        let sequential = builder.build();
+
+        let merged = documents
+            .iter()
+            .map(|document| {
+                let mut builder = RulesBuilder::new();
+                builder.feed(document);
+                builder.build()
+            })
+            .fold(Rules::new(), merge_rules);
+
+        assert_eq!(sequential, merged);
+    }
+}
@@ -0,0 +1,153 @@
+use std::collections::HashSet;
+
+use crate::wfc::wfc::rules::{Rules, WfcVector, END, START};
+
+use super::text_parse::tokenize;
+
+/// Marks a blank in a template, optionally followed by its length as {n} or {min,max}
+pub static BLANK: &str = "___";
+
+/// A part of a template
+#[derive(Debug, PartialEq, Eq)]
+pub enum TemplatePart {
+    /// A fixed word
+    Word(String),
+    /// Between [min] and [max] generated words
+    Blank { min: usize, max: usize },
+}
+
+/// Parses the length of a blank written as n or min,max
+fn parse_blank_length(length: &str) -> Result<(usize, usize), &'static str> {
+    let parse = |number: &str| {
+        number
+            .trim()
+            .parse::<usize>()
+            .map_err(|_| "A blank length is not a number")
+    };
+    let (min, max) = match length.split_once(',') {
+        Some((min, max)) => (parse(min)?, parse(max)?),
+        None => (parse(length)?, parse(length)?),
+    };
+
+    match min <= max {
+        true => Ok((min, max)),
+        false => Err("A blank is longer at minimum than at maximum"),
+    }
+}
+
+/// Parses a template like "the ___ ___{1,3} over the ___", tokenizing the fixed words like text
+pub fn parse_template(template: &str) -> Result<Vec<TemplatePart>, &'static str> {
+    let mut parts = Vec::<TemplatePart>::new();
+    let mut rest = template;
+
+    while let Some(start) = rest.find(BLANK) {
+        parts.extend(tokenize(&rest[..start]).into_iter().map(TemplatePart::Word));
+        rest = &rest[start + BLANK.len()..];
+
+        let (mut min, mut max) = (1, 1);
+        if let Some(length) = rest.strip_prefix('{') {
+            let end = length.find('}').ok_or("A blank length is not closed")?;
+            (min, max) = parse_blank_length(&length[..end])?;
+            rest = &length[end + 1..];
+        }
+        parts.push(TemplatePart::Blank { min, max });
+    }
+    parts.extend(tokenize(rest).into_iter().map(TemplatePart::Word));
+
+    Ok(parts)
+}
+
+/// Returns the vector of the template, with the fixed words collapsed and every blank as long as
+/// the matching length
+pub fn template_to_wfc_vector(
+    parts: &[TemplatePart],
+    lengths: &[usize],
+    rules: &Rules,
+) -> Result<WfcVector, &'static str> {
+    let all: HashSet<String> = rules.keys().cloned().collect();
+    let mut vector: WfcVector = vec![[START.to_string()].into_iter().collect()];
+    let mut lengths = lengths.iter();
+
+    for part in parts {
+        match part {
+            TemplatePart::Word(word) if !rules.contains_key(word) => {
+                return Err("The template has a word which is not in the rules")
+            }
+            TemplatePart::Word(word) => vector.push([word.clone()].into_iter().collect()),
+            TemplatePart::Blank { .. } => {
+                let length = lengths.next().ok_or("A blank has no length")?;
+                vector.extend((0..*length).map(|_| all.clone()));
+            }
+        }
+    }
+
+    vector.push([END.to_string()].into_iter().collect());
+    Ok(vector)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::wfc::parsing::text_parse::generate_rules;
+    use crate::wfc::wfc::rules::{END, START};
+
+    use super::{parse_template, template_to_wfc_vector, TemplatePart};
+
+    fn word(word: &str) -> TemplatePart {
+        TemplatePart::Word(word.to_string())
+    }
+
+    #[test]
+    fn test_parse_template_sanity() {
+        assert_eq!(
+            parse_template("The ___ ___{2} jumped over the ___{1,3}.").unwrap(),
+            vec![
+                word("the"),
+                TemplatePart::Blank { min: 1, max: 1 },
+                TemplatePart::Blank { min: 2, max: 2 },
+                word("jumped"),
+                word("over"),
+                word("the"),
+                TemplatePart::Blank { min: 1, max: 3 },
+                word("."),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_template_invalid() {
+        assert_eq!(
+            parse_template("the ___{2"),
+            Err("A blank length is not closed")
+        );
+        assert_eq!(
+            parse_template("the ___{x}"),
+            Err("A blank length is not a number")
+        );
+        assert_eq!(
+            parse_template("the ___{3,1}"),
+            Err("A blank is longer at minimum than at maximum")
+        );
+    }
+
+    #[test]
+    fn test_template_to_wfc_vector_sanity() {
+        let rules = generate_rules("the cat sat on the mat.".to_string());
+        let parts = parse_template("the ___{1,2} sat").unwrap();
+
+        let vector = template_to_wfc_vector(&parts, &[2], &rules).unwrap();
+
+        assert_eq!(vector.len(), 6);
+        assert!(vector[0].contains(START));
+        assert_eq!(vector[1].len(), 1);
+        assert_eq!(vector[2].len(), rules.len());
+        assert_eq!(vector[3].len(), rules.len());
+        assert!(vector[4].contains("sat"));
+        assert!(vector[5].contains(END));
+
+        let parts = parse_template("the dog ___").unwrap();
+        assert_eq!(
+            template_to_wfc_vector(&parts, &[1], &rules),
+            Err("The template has a word which is not in the rules")
+        );
+    }
+}
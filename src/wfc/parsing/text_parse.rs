@@ -3,33 +3,90 @@ use crate::wfc::wfc::{
     rules::{Allowed, Rules, END, START},
 };
 
-use super::post_processing::{remove_double_char, AFTER_TOKENS, BEFORE_TOKENS};
+use super::post_processing::{AFTER_TOKENS, BEFORE_TOKENS};
+
+/// Splits text into tokens as it is fed, so a text can be tokenized a chunk at a time
+#[derive(Default)]
+pub(crate) struct TextTokenizer {
+    /// The word which was not ended yet
+    word: String,
+    /// Wether a newline was seen which is dropped if it ends the text
+    pending_newline: bool,
+}
 
-/// Returns the given text as an array of tokens
-fn parse_text(mut text: String) -> Vec<String> {
-    text = text.to_lowercase();
-    if text.ends_with('\n') {
-        text.pop();
+impl TextTokenizer {
+    /// Emits the current word if there is one
+    fn end_word(&mut self, emit: &mut impl FnMut(String)) {
+        if !self.word.is_empty() {
+            emit(self.word.to_lowercase());
+            self.word.clear();
+        }
+    }
+
+    /// Emits the newline which turned out not to end the text
+    fn end_newline(&mut self, emit: &mut impl FnMut(String)) {
+        if self.pending_newline {
+            emit("\n".to_string());
+            self.pending_newline = false;
+        }
     }
 
-    for token in BEFORE_TOKENS {
-        let before_string = format!("{} ", &token.to_string().as_str());
-        text = text.replace(&token.to_string().as_str(), &before_string);
+    /// Tokenizes the next chunk of the text, emitting every token which is complete
+    pub(crate) fn feed(&mut self, text: &str, emit: &mut impl FnMut(String)) {
+        for char in text.chars() {
+            if char == '\n' {
+                self.end_word(emit);
+                self.end_newline(emit);
+                self.pending_newline = true;
+                continue;
+            }
+
+            self.end_newline(emit);
+            if char == ' ' {
+                self.end_word(emit);
+            } else if BEFORE_TOKENS.contains(&char) {
+                // Ends the word it is part of
+                self.word.push(char);
+                self.end_word(emit);
+            } else if AFTER_TOKENS.contains(&char) {
+                // Starts a new word
+                self.end_word(emit);
+                self.word.push(char);
+            } else {
+                self.word.push(char);
+            }
+        }
     }
 
-    for token in AFTER_TOKENS {
-        let after_string = format!(" {}", &token.to_string().as_str());
-        text = text.replace(&token.to_string().as_str(), &after_string);
+    /// Emits the last word of the text, dropping a trailing newline
+    pub(crate) fn finish(&mut self, emit: &mut impl FnMut(String)) {
+        self.end_word(emit);
+        self.pending_newline = false;
     }
+}
+
+/// Returns the tokens of a piece of text, without the start and the end
+pub fn tokenize(text: &str) -> Vec<String> {
+    let mut tokens = Vec::<String>::new();
+    let mut tokenizer = TextTokenizer::default();
 
-    text = remove_double_char(text, ' ');
-    text = format!("{START} {text} {END}");
+    let mut emit = |token| tokens.push(token);
+    tokenizer.feed(text, &mut emit);
+    tokenizer.finish(&mut emit);
 
-    text.split(" ").map(|s| s.to_string()).collect()
+    tokens
+}
+
+/// Returns the given text as an array of tokens
+fn parse_text(text: String) -> Vec<String> {
+    let mut tokens = vec![START.to_string()];
+    tokens.extend(tokenize(&text));
+    tokens.push(END.to_string());
+    tokens
 }
 
 /// Converts a vector of tokens into rules object
-fn convert_tokens_to_rules(tokens: &Vec<String>) -> Rules {
+pub(crate) fn convert_tokens_to_rules(tokens: &[String]) -> Rules {
     let mut rules = Rules::new();
 
     for (i, token) in tokens.iter().enumerate() {
@@ -46,6 +103,7 @@ fn convert_tokens_to_rules(tokens: &Vec<String>) -> Rules {
 
             if let Some(right) = get_right_neighbor(i, tokens.len()) {
                 let right_neighbor = tokens[right].clone();
+                *allowed.counts.entry(right_neighbor.clone()).or_insert(0) += 1;
                 let after = &mut allowed.after;
                 after.insert(right_neighbor);
             }
@@ -75,7 +133,18 @@ mod tests {
         wfc::rules::{END, START},
     };
 
-    use super::convert_tokens_to_rules;
+    use super::{convert_tokens_to_rules, tokenize};
+
+    #[test]
+    fn test_tokenize_sanity() {
+        assert_eq!(
+            tokenize("Once upon (a) time,"),
+            ["once", "upon", "(", "a", ")", "time", ","]
+                .map(|s| s.to_string())
+                .into_iter()
+                .collect::<Vec<_>>()
+        )
+    }
 
     #[test]
     fn test_parse_text_sanity() {
@@ -155,5 +224,8 @@ mod tests {
         assert!(rules.contains_key("\n"));
         assert!(rules["hello"].after.contains("there"));
         assert!(rules["hello"].after.contains("world"));
+        assert_eq!(rules["hello"].counts["there"], 1);
+        assert_eq!(rules["hello"].counts["world"], 1);
+        assert!(!rules[END].counts.contains_key(START));
     }
 }
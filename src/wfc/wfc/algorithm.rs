@@ -1,11 +1,19 @@
-use std::collections::HashSet;
+use std::str::FromStr;
 
 use rand::seq::SliceRandom;
+use rand::Rng;
 
-use super::rules::{Rules, WfcVector};
+use super::constraints::Constraints;
+use super::propagation::{Propagator, PLACEHOLDER};
+use super::rules::{CompiledRules, Rules, WfcVector};
 
-static PLACEHOLDER: String = String::new();
-use std::usize::MAX as USIZE_MAX_VALUE;
+/// How many times the vector is collapsed again from the start when the required words can't all
+/// be placed anymore, or a set is left without any word
+const CONSTRAINT_ATTEMPTS: usize = 10;
+/// The error when the required words can't all be placed
+const REQUIRED_ERROR: &str = "The required words can't all be placed";
+/// The error when removing the words which can't appear anymore leaves a set without any word
+const EXHAUSTED_ERROR: &str = "The maximum occurrences left a token without any word";
 
 /// Returns the left index (before)
 pub fn get_left_neighbor(index: usize) -> Option<usize> {
@@ -23,18 +31,6 @@ pub fn get_right_neighbor(index: usize, max_length: usize) -> Option<usize> {
     }
 }
 
-/// Returns valid neighbor indexes
-fn get_valid_neighbors(index: usize, max_length: usize) -> impl Iterator<Item = usize> {
-    let mut result = Vec::<usize>::new();
-    if index > 0 {
-        result.push(index - 1);
-    }
-    if index < (max_length - 1) {
-        result.push(index + 1);
-    }
-    result.into_iter()
-}
-
 /// Returns wether the vector is collapsed
 fn is_collapsed(wfc_vector: &WfcVector) -> bool {
     for set in wfc_vector {
@@ -45,36 +41,73 @@ fn is_collapsed(wfc_vector: &WfcVector) -> bool {
     true
 }
 
-/// Returns the next index to collapse, usually the one with the least number of words
-fn get_next_index(wfc_vector: &WfcVector) -> Option<usize> {
-    let mut minimum_length = USIZE_MAX_VALUE;
-    let mut minimum_indexes = Vec::<usize>::new();
+/// How the next set to collapse is chosen
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Heuristic {
+    /// The set with the least number of words, ties broken randomly
+    #[default]
+    MinimumEntropy,
+    /// The first uncollapsed set
+    LeftToRight,
+    /// Any uncollapsed set
+    Random,
+}
 
-    for (i, set) in wfc_vector.iter().enumerate() {
-        if set.len() < minimum_length && set.len() > 1 {
-            minimum_length = set.len();
-            minimum_indexes.clear();
-        }
-        if set.len() == minimum_length {
-            minimum_indexes.push(i);
+impl FromStr for Heuristic {
+    type Err = &'static str;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "entropy" => Ok(Self::MinimumEntropy),
+            "left-to-right" => Ok(Self::LeftToRight),
+            "random" => Ok(Self::Random),
+            _ => Err("Unknown heuristic"),
         }
     }
+}
 
-    minimum_indexes.choose(&mut rand::thread_rng()).map(|n| *n)
+/// Returns the next index to collapse according to the heuristic
+fn get_next_index(
+    wfc_vector: &WfcVector,
+    heuristic: Heuristic,
+    rng: &mut impl Rng,
+) -> Option<usize> {
+    let uncollapsed = (0..wfc_vector.len()).filter(|i| wfc_vector[*i].len() > 1);
+
+    match heuristic {
+        Heuristic::MinimumEntropy => {
+            let minimum_length = uncollapsed.clone().map(|i| wfc_vector[i].len()).min()?;
+            let minimum_indexes: Vec<usize> = uncollapsed
+                .filter(|i| wfc_vector[*i].len() == minimum_length)
+                .collect();
+            minimum_indexes.choose(rng).copied()
+        }
+        Heuristic::LeftToRight => uncollapsed.min(),
+        Heuristic::Random => uncollapsed.collect::<Vec<usize>>().choose(rng).copied(),
+    }
 }
 
-/// Collapse the set at the given location, returning the value
-fn collapse_at(wfc_vector: &mut WfcVector, index: usize) -> Result<String, &'static str> {
+/// Collapse the set at the given location to a value allowed by the constraints, returning the value
+fn collapse_at(
+    wfc_vector: &mut WfcVector,
+    index: usize,
+    constraints: &Constraints,
+    rng: &mut impl Rng,
+) -> Result<String, &'static str> {
     if index >= wfc_vector.len() {
         return Err("Index was out of range");
     }
-    let set = &wfc_vector[index];
-    let set_as_vec: Vec<&String> = set.iter().collect();
+    if wfc_vector[index].is_empty() {
+        return Err("Set was empty");
+    }
+    // Sorted so that the same random generator always picks the same value
+    let mut set_as_vec = constraints.get_candidates(wfc_vector, index);
+    set_as_vec.sort();
 
-    let value = set_as_vec.choose(&mut rand::thread_rng());
+    let value = set_as_vec.choose(rng);
 
     if value.is_none() {
-        return Err("Set was empty");
+        return Err("No value satisfies the constraints");
     }
 
     let value = String::from(*value.unwrap());
@@ -83,60 +116,6 @@ fn collapse_at(wfc_vector: &mut WfcVector, index: usize) -> Result<String, &'sta
     Ok(value)
 }
 
-/// Returns a set with the possible values based on neighbors
-fn get_valid_options_from_neighbors(
-    wfc_vector: &WfcVector,
-    rules: &Rules,
-    index: usize,
-) -> HashSet<String> {
-    let mut result = wfc_vector[index].clone();
-    let before = get_left_neighbor(index);
-    let after = get_right_neighbor(index, wfc_vector.len());
-
-    let all_keys = rules
-        .keys()
-        .into_iter()
-        .cloned()
-        .collect::<HashSet<String>>();
-
-    if before.is_some() {
-        let before = before.unwrap();
-        let set = &(wfc_vector[before]);
-
-        let mut sum = HashSet::<String>::new();
-        for word in set {
-            let after_rules = match word.is_empty() {
-                true => &all_keys,
-                false => &(rules[word].after),
-            };
-
-            sum.extend(after_rules.iter().map(|s| s.to_string()));
-        }
-        result = result.intersection(&sum).map(|s| s.to_string()).collect();
-    }
-
-    if after.is_some() {
-        let after = after.unwrap();
-        let set = &(wfc_vector[after]);
-        let mut sum = HashSet::<String>::new();
-        for word in set {
-            let before_rules = match word.is_empty() {
-                true => &all_keys,
-                false => &(rules[word].before),
-            };
-
-            sum.extend(before_rules.iter().map(|s| s.to_string()));
-        }
-        result = result.intersection(&sum).map(|s| s.to_string()).collect();
-    }
-
-    if result.is_empty() {
-        result.insert(PLACEHOLDER.clone());
-    }
-
-    result
-}
-
 /// Converts a WfcVector to a vector of strings.
 fn flatten_wfc_vector(wfc_vector: WfcVector) -> Result<Vec<String>, &'static str> {
     let mut result = Vec::<String>::new();
@@ -151,61 +130,187 @@ fn flatten_wfc_vector(wfc_vector: WfcVector) -> Result<Vec<String>, &'static str
     Ok(result)
 }
 
-/// Limits the possible values based on the last collapsed value
-fn propagate(wfc_vector: &mut WfcVector, rules: &Rules, last_collapse_index: usize) {
-    let mut stack = Vec::<usize>::new();
-    let mut current_index = Some(last_collapse_index);
-
-    while current_index.is_some() {
-        let unwrapped_current_index = current_index.unwrap();
-        let neighbors = get_valid_neighbors(unwrapped_current_index, wfc_vector.len());
-
-        for neighbor_index in neighbors {
-            let set = &wfc_vector[neighbor_index];
-            let new_set = get_valid_options_from_neighbors(wfc_vector, rules, neighbor_index);
-            let new_set = new_set.iter().map(|s| s.to_string()).collect();
-
-            if !stack.contains(&neighbor_index) & (*set != new_set) {
-                stack.push(neighbor_index);
-            }
+/// Removes the words which can't appear anymore from the uncollapsed sets, propagating the changes
+fn remove_exhausted(
+    wfc_vector: &mut WfcVector,
+    propagator: &mut Propagator,
+    constraints: &Constraints,
+) {
+    let exhausted = constraints.get_exhausted(wfc_vector);
+    if exhausted.is_empty() {
+        return;
+    }
 
-            wfc_vector[neighbor_index] = new_set;
+    for index in 0..wfc_vector.len() {
+        let set = &mut wfc_vector[index];
+        if set.len() > 1 && exhausted.iter().any(|word| set.contains(word)) {
+            set.retain(|word| !exhausted.contains(word));
+            propagator.sync(wfc_vector, index);
         }
-
-        current_index = stack.pop();
     }
+    propagator.propagate(wfc_vector);
 }
 
-/// Iterates over the vector and propagate it until it is collapsed, returning the collapsed vector
-pub fn iterate(mut wfc_vector: WfcVector, rules: &Rules) -> Result<Vec<String>, &'static str> {
-    // Propagate the start and end
-    let length = wfc_vector.len();
-    propagate(&mut wfc_vector, rules, 0);
-    propagate(&mut wfc_vector, rules, length - 1);
-
-    while !is_collapsed(&wfc_vector) {
-        let index = get_next_index(&wfc_vector);
+/// Collapses the propagated vector set by set, failing as soon as the required words can't all be
+/// placed anymore or a set has no word left
+fn collapse_all(
+    mut wfc_vector: WfcVector,
+    mut propagator: Propagator,
+    constraints: &Constraints,
+    heuristic: Heuristic,
+    rng: &mut impl Rng,
+) -> Result<WfcVector, &'static str> {
+    loop {
+        if !constraints.can_place_required(&wfc_vector) {
+            return Err(REQUIRED_ERROR);
+        }
+        if is_collapsed(&wfc_vector) {
+            return Ok(wfc_vector);
+        }
+
+        let index = get_next_index(&wfc_vector, heuristic, rng);
         if index.is_none() {
             return Err("No index was valid during iteration");
         }
         let index = index.unwrap();
-        collapse_at(&mut wfc_vector, index)?;
-        propagate(&mut wfc_vector, rules, index);
+        collapse_at(&mut wfc_vector, index, constraints, rng)?;
+        propagator.sync(&mut wfc_vector, index);
+        propagator.propagate(&mut wfc_vector);
+        remove_exhausted(&mut wfc_vector, &mut propagator, constraints);
+        if wfc_vector.iter().any(|set| set.contains(&PLACEHOLDER)) {
+            return Err(EXHAUSTED_ERROR);
+        }
+    }
+}
+
+/// Iterates over the vector and propagate it until it is collapsed, returning the collapsed vector
+pub fn iterate(wfc_vector: WfcVector, rules: &Rules) -> Result<Vec<String>, &'static str> {
+    iterate_with_constraints(wfc_vector, rules, &Constraints::default())
+}
+
+/// Iterates over the vector like [iterate], while enforcing the given global constraints
+pub fn iterate_with_constraints(
+    wfc_vector: WfcVector,
+    rules: &Rules,
+    constraints: &Constraints,
+) -> Result<Vec<String>, &'static str> {
+    iterate_with(
+        wfc_vector,
+        rules,
+        &CompiledRules::new(rules),
+        constraints,
+        Heuristic::default(),
+        &mut rand::thread_rng(),
+    )
+}
+
+/// Propagates the rules through the whole vector before anything is collapsed. On a chain of sets
+/// this leaves only the words lying on a path from the first set to the last, so a set left
+/// without any word means no text of this length fits the rules and the sets.
+pub fn propagate_all<'a>(
+    wfc_vector: &mut WfcVector,
+    compiled_rules: &'a CompiledRules,
+) -> Result<Propagator<'a>, &'static str> {
+    let propagator = Propagator::new(wfc_vector, compiled_rules);
+    if wfc_vector.iter().any(|set| set.contains(&PLACEHOLDER)) {
+        return Err("No text of this length fits the rules");
+    }
+    Ok(propagator)
+}
+
+/// Iterates over the vector like [iterate_with_constraints], with the rules already compiled,
+/// choosing the sets to collapse with the heuristic and every random choice with the given random
+/// generator
+pub fn iterate_with(
+    mut wfc_vector: WfcVector,
+    rules: &Rules,
+    compiled_rules: &CompiledRules,
+    constraints: &Constraints,
+    heuristic: Heuristic,
+    rng: &mut impl Rng,
+) -> Result<Vec<String>, &'static str> {
+    constraints.remove_banned(&mut wfc_vector);
+
+    if wfc_vector.iter().any(|set| set.is_empty()) {
+        return Err("The constraints can't be satisfied");
+    }
+    let propagator = propagate_all(&mut wfc_vector, compiled_rules)?;
+
+    let mut collapsed = Err(REQUIRED_ERROR);
+    for _ in 0..CONSTRAINT_ATTEMPTS {
+        collapsed = collapse_all(
+            wfc_vector.clone(),
+            propagator.clone(),
+            constraints,
+            heuristic,
+            rng,
+        );
+        if collapsed != Err(REQUIRED_ERROR) && collapsed != Err(EXHAUSTED_ERROR) {
+            break;
+        }
     }
+    let wfc_vector = collapsed?;
+
+    let result = flatten_wfc_vector(wfc_vector)?;
+    // Collapsing keeps the vector arc consistent and attempts leaving a set empty are dropped
+    debug_assert!(
+        verify(&result, rules).is_empty(),
+        "Two adjacent words were collapsed without being allowed by the rules"
+    );
+    constraints.check(&result)?;
+    Ok(result)
+}
+
+/// An adjacent pair of words which the rules don't allow
+#[derive(Debug, PartialEq, Eq)]
+pub struct InvalidAdjacency {
+    /// The index of the first word of the pair
+    pub index: usize,
+    pub before: String,
+    pub after: String,
+}
 
-    flatten_wfc_vector(wfc_vector)
+/// Returns every adjacent pair of the words which the rules don't allow, including pairs with
+/// contradictions (empty words)
+pub fn verify(words: &[String], rules: &Rules) -> Vec<InvalidAdjacency> {
+    words
+        .windows(2)
+        .enumerate()
+        .filter(|(_, pair)| {
+            let allowed_after = rules
+                .get(&pair[0])
+                .is_some_and(|allowed| allowed.after.contains(&pair[1]));
+            let allowed_before = rules
+                .get(&pair[1])
+                .is_some_and(|allowed| allowed.before.contains(&pair[0]));
+            !(allowed_after && allowed_before)
+        })
+        .map(|(index, pair)| InvalidAdjacency {
+            index,
+            before: pair[0].clone(),
+            after: pair[1].clone(),
+        })
+        .collect()
 }
 
 #[cfg(test)]
 mod tests {
     use std::collections::HashMap;
 
+    use rand::thread_rng;
+
+    use crate::wfc::parsing::text_parse::generate_rules;
     use crate::wfc::wfc::{
-        algorithm::{collapse_at, get_valid_neighbors, is_collapsed, PLACEHOLDER},
-        rules::{Allowed, Rules, END, START},
+        algorithm::{collapse_at, is_collapsed},
+        constraints::Constraints,
+        propagation::PLACEHOLDER,
+        rules::{add_transition, generate_wfc_vector, Allowed, CompiledRules, Rules, END, START},
     };
 
-    use super::{flatten_wfc_vector, get_valid_options_from_neighbors, propagate, WfcVector};
+    use super::{
+        flatten_wfc_vector, get_next_index, iterate, iterate_with_constraints, propagate_all,
+        verify, Heuristic, InvalidAdjacency, WfcVector,
+    };
 
     fn get_rules() -> Rules {
         let mut rules = HashMap::<String, Allowed>::new();
@@ -262,22 +367,7 @@ mod tests {
     }
 
     #[test]
-    fn test_get_valid_neighbors_sanity() {
-        assert_eq!(
-            get_valid_neighbors(2, 100).into_iter().collect::<Vec<_>>(),
-            vec![1, 3]
-        );
-        assert_eq!(
-            get_valid_neighbors(0, 100).into_iter().collect::<Vec<_>>(),
-            vec![1]
-        );
-        assert_eq!(
-            get_valid_neighbors(1, 2).into_iter().collect::<Vec<_>>(),
-            vec![0]
-        );
-    }
-
-    #[test]
+    #[allow(clippy::bool_assert_comparison)]
     fn test_is_collapsed_sanity() {
         let mut vector: WfcVector = vec![
             vec!["hello".to_string()].into_iter().collect(),
@@ -291,6 +381,34 @@ mod tests {
         assert_eq!(is_collapsed(&vector), false);
     }
 
+    #[test]
+    fn test_get_next_index_sanity() {
+        let vector: WfcVector = vec![
+            vec!["hello".to_string()].into_iter().collect(),
+            vec!["a".to_string(), "b".to_string(), "c".to_string()]
+                .into_iter()
+                .collect(),
+            vec!["a".to_string(), "b".to_string()].into_iter().collect(),
+            vec!["world".to_string()].into_iter().collect(),
+        ];
+        let mut rng = thread_rng();
+
+        assert_eq!(
+            get_next_index(&vector, Heuristic::MinimumEntropy, &mut rng),
+            Some(2)
+        );
+        assert_eq!(
+            get_next_index(&vector, Heuristic::LeftToRight, &mut rng),
+            Some(1)
+        );
+        let index = get_next_index(&vector, Heuristic::Random, &mut rng);
+        assert!(index == Some(1) || index == Some(2));
+        assert_eq!(
+            get_next_index(&vector[..1].to_vec(), Heuristic::Random, &mut rng),
+            None
+        );
+    }
+
     #[test]
     fn test_collapse_at_sanity() {
         let mut vector: WfcVector = vec![
@@ -301,7 +419,7 @@ mod tests {
         ];
         assert_ne!(vector[0].len(), 1);
 
-        collapse_at(&mut vector, 0).unwrap();
+        collapse_at(&mut vector, 0, &Constraints::default(), &mut thread_rng()).unwrap();
 
         assert_eq!(vector[0].len(), 1);
     }
@@ -310,7 +428,7 @@ mod tests {
     fn test_collapse_at_empty_set() {
         let mut vector: WfcVector = vec![vec![].into_iter().collect()];
 
-        let result = collapse_at(&mut vector, 0);
+        let result = collapse_at(&mut vector, 0, &Constraints::default(), &mut thread_rng());
 
         assert_eq!(result, Err("Set was empty"));
     }
@@ -319,11 +437,38 @@ mod tests {
     fn test_collapse_at_invalid_index() {
         let mut vector: WfcVector = vec![];
 
-        let result = collapse_at(&mut vector, 100);
+        let result = collapse_at(&mut vector, 100, &Constraints::default(), &mut thread_rng());
 
         assert_eq!(result, Err("Index was out of range"));
     }
 
+    #[test]
+    fn test_collapse_at_constraints() {
+        let mut vector: WfcVector = vec![
+            vec!["hello".to_string()].into_iter().collect(),
+            vec!["hello".to_string(), "world".to_string()]
+                .into_iter()
+                .collect(),
+        ];
+        let constraints = Constraints {
+            no_repeat_ngram: Some(1),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            collapse_at(&mut vector, 1, &constraints, &mut thread_rng()),
+            Ok("world".to_string())
+        );
+
+        let mut vector: WfcVector = vec![
+            vec!["hello".to_string()].into_iter().collect(),
+            vec!["hello".to_string()].into_iter().collect(),
+        ];
+        let result = collapse_at(&mut vector, 1, &constraints, &mut thread_rng());
+
+        assert_eq!(result, Err("No value satisfies the constraints"));
+    }
+
     #[test]
     fn test_flatten_wfc_vector_sanity() {
         let vector: WfcVector = vec![
@@ -349,54 +494,156 @@ mod tests {
     }
 
     #[test]
-    fn test_get_valid_options_from_neighbors_sanity() {
+    fn test_iterate_sanity() {
         let rules = get_rules();
-        let vector: WfcVector = vec![
-            vec!["hello".to_string()].into_iter().collect(),
-            rules.keys().cloned().into_iter().collect(),
-            vec!["!".to_string(), END.to_string()].into_iter().collect(),
-        ];
-        let result = get_valid_options_from_neighbors(&vector, &rules, 1);
 
-        assert_eq!(result.len(), 2);
-        assert!(result.contains(&"world".to_string()));
-        assert!(result.contains(&"there".to_string()));
+        for _ in 0..10 {
+            let vector = generate_wfc_vector(&rules, 3);
+
+            assert_eq!(
+                iterate(vector, &rules).unwrap(),
+                vec![
+                    START.to_string(),
+                    "hello".to_string(),
+                    "world".to_string(),
+                    "!".to_string(),
+                    END.to_string()
+                ]
+            );
+        }
     }
 
     #[test]
-    fn test_get_valid_options_from_neighbors_impossible() {
+    fn test_iterate_with_constraints_banned() {
         let rules = get_rules();
-        let vector: WfcVector = vec![
-            vec!["!".to_string()].into_iter().collect(),
-            rules.keys().cloned().into_iter().collect(),
-            vec![END.to_string()].into_iter().collect(),
+        let constraints = Constraints {
+            banned: ["world".to_string()].into_iter().collect(),
+            ..Default::default()
+        };
+
+        for _ in 0..10 {
+            let vector = generate_wfc_vector(&rules, 2);
+            let result = iterate_with_constraints(vector, &rules, &constraints).unwrap();
+
+            assert_eq!(
+                result,
+                vec![
+                    START.to_string(),
+                    "hello".to_string(),
+                    "there".to_string(),
+                    END.to_string()
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn test_iterate_with_constraints_impossible() {
+        let rules = get_rules();
+        let constraints = Constraints {
+            required: ["there".to_string()].into_iter().collect(),
+            ..Default::default()
+        };
+
+        let vector = generate_wfc_vector(&rules, 3);
+        assert_eq!(
+            iterate_with_constraints(vector, &rules, &constraints),
+            Err("The required words can't all be placed")
+        );
+
+        let vector = generate_wfc_vector(&rules, 2);
+        assert!(iterate_with_constraints(vector, &rules, &constraints).is_ok());
+    }
+
+    #[test]
+    fn test_iterate_with_constraints_max_occurrences() {
+        // b c b, b c c and b d b, so every text repeats a word
+        let mut rules = Rules::new();
+        let transitions = [
+            (START, "b"),
+            ("b", "c"),
+            ("b", "d"),
+            ("c", "b"),
+            ("c", "c"),
+            ("d", "b"),
+            ("b", END),
+            ("c", END),
         ];
-        let result = get_valid_options_from_neighbors(&vector, &rules, 1);
+        for (before, after) in transitions {
+            add_transition(&mut rules, before, after, 0);
+        }
+        let constraints = Constraints {
+            max_occurrences: Some(1),
+            ..Default::default()
+        };
+
+        for _ in 0..20 {
+            let vector = generate_wfc_vector(&rules, 3);
+            assert!(iterate_with_constraints(vector, &rules, &constraints).is_err());
+        }
+    }
+
+    #[test]
+    fn test_propagate_all_sanity() {
+        let rules = generate_rules("the cat sat. a dog".to_string());
+        let compiled_rules = CompiledRules::new(&rules);
+        let mut vector = generate_wfc_vector(&rules, 6);
+
+        propagate_all(&mut vector, &compiled_rules).unwrap();
 
-        assert_eq!(result.len(), 1);
-        assert_eq!(result.into_iter().next().unwrap(), PLACEHOLDER);
+        let words = [START, "the", "cat", "sat", ".", "a", "dog", END];
+        for (set, word) in vector.iter().zip(words) {
+            assert_eq!(set.len(), 1);
+            assert!(set.contains(word));
+        }
     }
 
     #[test]
-    fn test_propagate_sanity() {
+    fn test_propagate_all_impossible() {
+        let rules = generate_rules("the cat sat".to_string());
+        let compiled_rules = CompiledRules::new(&rules);
+
+        let mut vector = generate_wfc_vector(&rules, 2);
+        assert_eq!(
+            propagate_all(&mut vector, &compiled_rules).err(),
+            Some("No text of this length fits the rules")
+        );
+
+        let mut vector = generate_wfc_vector(&rules, 3);
+        assert!(propagate_all(&mut vector, &compiled_rules).is_ok());
+    }
+
+    #[test]
+    fn test_verify_sanity() {
         let rules = get_rules();
-        let mut vector: WfcVector = vec![
-            vec![START.to_string()].into_iter().collect(),
-            vec!["hello".to_string()].into_iter().collect(),
-            rules.keys().cloned().into_iter().collect(),
-            vec!["!".to_string(), END.to_string()].into_iter().collect(),
-        ];
-        propagate(&mut vector, &rules, 1);
+        let words = [START, "hello", "world", "!", END].map(|s| s.to_string());
+
+        assert_eq!(verify(&words, &rules), vec![]);
+    }
+
+    #[test]
+    fn test_verify_invalid() {
+        let rules = get_rules();
+        let words = [START, "hello", "!", PLACEHOLDER.as_str(), END].map(|s| s.to_string());
 
         assert_eq!(
-            vector,
+            verify(&words, &rules),
             vec![
-                vec![START.to_string()].into_iter().collect(),
-                vec!["hello".to_string()].into_iter().collect(),
-                vec!["world".to_string(), "there".to_string()]
-                    .into_iter()
-                    .collect(),
-                vec!["!".to_string(), END.to_string()].into_iter().collect(),
+                InvalidAdjacency {
+                    index: 1,
+                    before: "hello".to_string(),
+                    after: "!".to_string()
+                },
+                InvalidAdjacency {
+                    index: 2,
+                    before: "!".to_string(),
+                    after: PLACEHOLDER.clone()
+                },
+                InvalidAdjacency {
+                    index: 3,
+                    before: PLACEHOLDER.clone(),
+                    after: END.to_string()
+                },
             ]
         );
     }
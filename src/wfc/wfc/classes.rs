@@ -0,0 +1,249 @@
+use rand::seq::SliceRandom;
+use rand::Rng;
+use std::collections::{HashMap, HashSet};
+
+use super::pruning::get_word_counts;
+use super::rules::{add_transition, Rules, END, START};
+
+/// A map of words to their class (like NOUN or VERB), words without a class are their own class
+pub type ClassMap = HashMap<String, String>;
+
+/// The words of every class, with how many times each appeared in the corpus
+pub type Members = HashMap<String, HashMap<String, usize>>;
+
+/// Returns the class of the word, the start and the end always being their own class
+pub fn get_class<'a>(word: &'a str, classes: &'a ClassMap) -> &'a str {
+    match word == START || word == END {
+        true => word,
+        false => classes.get(word).map(String::as_str).unwrap_or(word),
+    }
+}
+
+/// Returns the rules between the classes of the words, with the counts of the transitions between
+/// the words of two classes added up, and the words of every class. Fails if a class is named
+/// like a word left without a class, which the class would be mistaken for.
+pub fn classify_rules(rules: &Rules, classes: &ClassMap) -> Result<(Rules, Members), &'static str> {
+    if classes
+        .values()
+        .any(|class| rules.contains_key(class) && !classes.contains_key(class))
+    {
+        return Err("A class is named like a word which is in no class");
+    }
+
+    let mut class_rules = Rules::new();
+    for (word, allowed) in rules {
+        for after in &allowed.after {
+            let count = match word == END && after == START {
+                true => 0,
+                false => allowed.counts.get(after).copied().unwrap_or(0),
+            };
+            add_transition(
+                &mut class_rules,
+                get_class(word, classes),
+                get_class(after, classes),
+                count,
+            );
+        }
+    }
+
+    let mut members = Members::new();
+    for (word, count) in get_word_counts(rules) {
+        if let Some(class) = classes.get(&word) {
+            members
+                .entry(class.clone())
+                .or_default()
+                .insert(word, count);
+        }
+    }
+
+    Ok((class_rules, members))
+}
+
+/// Returns how alike two sets are, from 0 for nothing in common to 1 for the same sets
+fn get_jaccard_similarity(a: &HashSet<String>, b: &HashSet<String>) -> f64 {
+    let union = a.union(b).count();
+    match union {
+        0 => 0.0,
+        union => a.intersection(b).count() as f64 / union as f64,
+    }
+}
+
+/// Returns how alike the neighbourhoods of two words are, averaging the similarity of the words
+/// they follow and of the words they precede
+fn get_similarity(rules: &Rules, a: &str, b: &str) -> f64 {
+    (get_jaccard_similarity(&rules[a].before, &rules[b].before)
+        + get_jaccard_similarity(&rules[a].after, &rules[b].after))
+        / 2.0
+}
+
+/// Returns the name of the cluster, with a space so that no token of the tokenizers is named the same
+fn get_cluster_name(i: usize) -> String {
+    format!("<cluster {i}>")
+}
+
+/// Groups the words into [count] clusters named <cluster 0>, <cluster 1>, ... by how alike their
+/// neighbourhoods are.
+/// The most frequent words seed the clusters and every other word joins the cluster of the seed
+/// it is the most alike, words alike no seed being left without a class.
+pub fn cluster_words(rules: &Rules, count: usize) -> ClassMap {
+    let word_counts = get_word_counts(rules);
+    let mut words: Vec<&String> = rules
+        .keys()
+        .filter(|word| *word != START && *word != END)
+        .collect();
+    words.sort_by(|a, b| word_counts[*b].cmp(&word_counts[*a]).then(a.cmp(b)));
+
+    let seeds = &words[..count.min(words.len())];
+    let mut classes: ClassMap = seeds
+        .iter()
+        .enumerate()
+        .map(|(i, seed)| (seed.to_string(), get_cluster_name(i)))
+        .collect();
+
+    for word in &words[seeds.len()..] {
+        let mut best: Option<(usize, f64)> = None;
+        for (i, seed) in seeds.iter().enumerate() {
+            let similarity = get_similarity(rules, word, seed);
+            if similarity > best.map_or(0.0, |(_, best)| best) {
+                best = Some((i, similarity));
+            }
+        }
+        if let Some((i, _)) = best {
+            classes.insert(word.to_string(), get_cluster_name(i));
+        }
+    }
+
+    classes
+}
+
+/// Returns the class of every word of the members
+pub fn members_to_class_map(members: &Members) -> ClassMap {
+    members
+        .iter()
+        .flat_map(|(class, words)| words.keys().map(|word| (word.clone(), class.clone())))
+        .collect()
+}
+
+/// Replaces every class of the tokens with one of its words, picked according to how often each
+/// appeared. Tokens which are not classes are kept.
+pub fn sample_members(tokens: Vec<String>, members: &Members, rng: &mut impl Rng) -> Vec<String> {
+    tokens
+        .into_iter()
+        .map(|token| {
+            let words = match members.get(&token) {
+                Some(words) if !words.is_empty() => words,
+                _ => return token,
+            };
+            let mut sorted: Vec<(&String, &usize)> = words.iter().collect();
+            sorted.sort();
+            sorted
+                .choose_weighted(rng, |(_, count)| (**count).max(1))
+                .map(|(word, _)| (*word).clone())
+                .unwrap_or(token)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use crate::wfc::parsing::text_parse::generate_rules;
+    use crate::wfc::wfc::rules::{END, START};
+
+    use super::{classify_rules, cluster_words, members_to_class_map, sample_members, ClassMap};
+
+    fn get_classes() -> ClassMap {
+        [
+            ("cat", "NOUN"),
+            ("dog", "NOUN"),
+            ("mat", "NOUN"),
+            ("sat", "VERB"),
+            ("ate", "VERB"),
+        ]
+        .into_iter()
+        .map(|(word, class)| (word.to_string(), class.to_string()))
+        .collect()
+    }
+
+    #[test]
+    fn test_classify_rules_sanity() {
+        let rules = generate_rules("the cat sat on the mat. the dog ate".to_string());
+
+        let (class_rules, members) = classify_rules(&rules, &get_classes()).unwrap();
+
+        assert!(!class_rules.contains_key("cat"));
+        assert!(class_rules["the"].after.contains("NOUN"));
+        assert_eq!(class_rules["the"].counts["NOUN"], 3);
+        assert!(class_rules["NOUN"].after.contains("VERB"));
+        assert!(class_rules["NOUN"].after.contains("."));
+        assert!(class_rules["VERB"].after.contains(END));
+        assert!(class_rules[END].after.contains(START));
+        assert_eq!(class_rules[END].counts.get(START), None);
+
+        assert_eq!(members["NOUN"].len(), 3);
+        assert_eq!(members["NOUN"]["cat"], 1);
+        assert_eq!(members["VERB"]["ate"], 1);
+        assert!(!members.contains_key("the"));
+        assert_eq!(members_to_class_map(&members), get_classes());
+    }
+
+    #[test]
+    fn test_classify_rules_collision() {
+        let rules = generate_rules("the cat sat. the noun".to_string());
+        let classes: ClassMap = [("cat", "noun"), ("sat", START)]
+            .into_iter()
+            .map(|(word, class)| (word.to_string(), class.to_string()))
+            .collect();
+
+        assert_eq!(
+            classify_rules(&rules, &classes).err(),
+            Some("A class is named like a word which is in no class")
+        );
+
+        let classes: ClassMap = [("cat", "noun"), ("noun", "noun")]
+            .into_iter()
+            .map(|(word, class)| (word.to_string(), class.to_string()))
+            .collect();
+        let (_, members) = classify_rules(&rules, &classes).unwrap();
+        assert_eq!(members["noun"].len(), 2);
+    }
+
+    #[test]
+    fn test_sample_members_sanity() {
+        let rules = generate_rules("the cat sat on the mat. the dog ate".to_string());
+        let (_, members) = classify_rules(&rules, &get_classes()).unwrap();
+        let tokens: Vec<String> = [START, "the", "NOUN", "VERB", END]
+            .into_iter()
+            .map(String::from)
+            .collect();
+
+        let sample = sample_members(tokens, &members, &mut StdRng::seed_from_u64(3));
+
+        assert_eq!(sample[0], START);
+        assert_eq!(sample[1], "the");
+        assert!(members["NOUN"].contains_key(&sample[2]));
+        assert!(members["VERB"].contains_key(&sample[3]));
+        assert_eq!(sample[4], END);
+    }
+
+    #[test]
+    fn test_cluster_words_sanity() {
+        let rules =
+            generate_rules("the cat sat. the dog sat. the cat ate. the dog ate. a".to_string());
+
+        let classes = cluster_words(&rules, 4);
+
+        assert_eq!(classes["."], "<cluster 0>");
+        assert_eq!(classes["the"], "<cluster 1>");
+        assert_eq!(classes["ate"], "<cluster 2>");
+        assert_eq!(classes["sat"], "<cluster 2>");
+        assert_eq!(classes["cat"], "<cluster 3>");
+        assert_eq!(classes["dog"], "<cluster 3>");
+        assert_eq!(classes["a"], "<cluster 1>");
+        assert!(!classes.contains_key(START));
+
+        assert_eq!(cluster_words(&rules, 100).len(), rules.len() - 2);
+    }
+}
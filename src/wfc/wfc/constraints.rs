@@ -0,0 +1,308 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+use super::rules::{WfcVector, END, START};
+
+/// Global constraints on the generated text, enforced on top of the adjacency rules
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Constraints {
+    /// Words which must never appear
+    pub banned: HashSet<String>,
+    /// Words which must appear at least once
+    pub required: HashSet<String>,
+    /// The maximum number of times any single word may appear
+    pub max_occurrences: Option<usize>,
+    /// Forbids any sequence of up to this many words from repeating immediately after itself
+    pub no_repeat_ngram: Option<usize>,
+}
+
+/// Returns the value of the set if it is collapsed
+fn get_collapsed(set: &HashSet<String>) -> Option<&String> {
+    match set.len() == 1 {
+        true => set.iter().next(),
+        false => None,
+    }
+}
+
+/// Returns wether the word counts towards the constraints (start, end and contradictions do not)
+fn is_constrained(word: &str) -> bool {
+    !word.is_empty() && word != START && word != END
+}
+
+/// Returns wether the words contain a sequence of up to [max_size] words ending or starting at the
+/// index which is immediately repeated
+fn has_repetition(words: &[Option<&String>], index: usize, max_size: usize) -> bool {
+    for size in 1..=max_size {
+        let first = (index + 1).saturating_sub(2 * size);
+        for start in first..=index {
+            if start + 2 * size > words.len() {
+                break;
+            }
+            let repeated = (0..size).all(|i| {
+                let (left, right) = (words[start + i], words[start + size + i]);
+                left.is_some() && left == right
+            });
+            if repeated {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+/// Returns how many uncollapsed sets, other than the one at [except], could hold one of the words
+fn count_holders(wfc_vector: &WfcVector, words: &[&String], except: Option<usize>) -> usize {
+    (0..wfc_vector.len())
+        .filter(|i| Some(*i) != except && wfc_vector[*i].len() > 1)
+        .filter(|i| words.iter().any(|word| wfc_vector[*i].contains(*word)))
+        .count()
+}
+
+impl Constraints {
+    /// Removes the banned words from every uncollapsed set of the vector
+    pub fn remove_banned(&self, wfc_vector: &mut WfcVector) {
+        for set in wfc_vector.iter_mut().filter(|set| set.len() > 1) {
+            set.retain(|word| !self.banned.contains(word));
+        }
+    }
+
+    /// Returns how many times each word appears in the collapsed sets
+    fn count_collapsed(wfc_vector: &WfcVector) -> HashMap<&String, usize> {
+        let mut counts = HashMap::<&String, usize>::new();
+        for word in wfc_vector.iter().filter_map(get_collapsed) {
+            *counts.entry(word).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    /// Returns the words which reached the maximum occurrences and can't appear anymore
+    pub fn get_exhausted(&self, wfc_vector: &WfcVector) -> HashSet<String> {
+        let max_occurrences = match self.max_occurrences {
+            Some(max_occurrences) => max_occurrences,
+            None => return HashSet::new(),
+        };
+
+        Self::count_collapsed(wfc_vector)
+            .into_iter()
+            .filter(|(word, count)| is_constrained(word) && *count >= max_occurrences)
+            .map(|(word, _)| word.clone())
+            .collect()
+    }
+
+    /// Returns the values the set at the index may collapse to, preferring required words which are
+    /// still missing
+    pub fn get_candidates<'a>(&self, wfc_vector: &'a WfcVector, index: usize) -> Vec<&'a String> {
+        let mut candidates: Vec<&String> = wfc_vector[index].iter().collect();
+
+        if let Some(max_size) = self.no_repeat_ngram {
+            let mut words: Vec<Option<&String>> = wfc_vector
+                .iter()
+                .map(|set| get_collapsed(set).filter(|word| is_constrained(word)))
+                .collect();
+            candidates.retain(|candidate| {
+                words[index] = Some(candidate);
+                !is_constrained(candidate) || !has_repetition(&words, index, max_size)
+            });
+        }
+
+        let missing = self.get_missing(wfc_vector);
+        if !missing.is_empty() {
+            let required: Vec<&String> = candidates
+                .iter()
+                .filter(|word| missing.contains(word))
+                .cloned()
+                .collect();
+            if !required.is_empty() {
+                return required;
+            }
+
+            // Keeps enough of the other sets for the required words still missing
+            if count_holders(wfc_vector, &missing, Some(index)) < missing.len() {
+                return Vec::new();
+            }
+        }
+
+        candidates
+    }
+
+    /// Returns the required words which are not collapsed anywhere yet
+    fn get_missing(&self, wfc_vector: &WfcVector) -> Vec<&String> {
+        if self.required.is_empty() {
+            return Vec::new();
+        }
+        let counts = Self::count_collapsed(wfc_vector);
+        self.required
+            .iter()
+            .filter(|word| !counts.contains_key(word))
+            .collect()
+    }
+
+    /// Returns wether every required word still missing can still be placed, each in its own
+    /// uncollapsed set
+    pub fn can_place_required(&self, wfc_vector: &WfcVector) -> bool {
+        let missing = self.get_missing(wfc_vector);
+        missing
+            .iter()
+            .all(|word| count_holders(wfc_vector, &[word], None) > 0)
+            && count_holders(wfc_vector, &missing, None) >= missing.len()
+    }
+
+    /// Checks that a generated vector of words has no banned word and every required word
+    pub fn check_words(&self, words: &[String]) -> Result<(), &'static str> {
+        if words.iter().any(|word| self.banned.contains(word)) {
+            return Err("The result contains a banned word");
+        }
+        if self.required.iter().any(|word| !words.contains(word)) {
+            return Err("The result is missing a required word");
+        }
+        Ok(())
+    }
+
+    /// Checks that a generated vector of words satisfies the constraints
+    pub fn check(&self, words: &[String]) -> Result<(), &'static str> {
+        self.check_words(words)?;
+
+        if let Some(max_occurrences) = self.max_occurrences {
+            let mut counts = HashMap::<&String, usize>::new();
+            for word in words.iter().filter(|word| is_constrained(word)) {
+                *counts.entry(word).or_insert(0) += 1;
+            }
+            if counts.values().any(|count| *count > max_occurrences) {
+                return Err("The result has a word appearing too many times");
+            }
+        }
+
+        if let Some(max_size) = self.no_repeat_ngram {
+            let words: Vec<Option<&String>> = words
+                .iter()
+                .map(|word| Some(word).filter(|word| is_constrained(word)))
+                .collect();
+            if (0..words.len()).any(|i| has_repetition(&words, i, max_size)) {
+                return Err("The result has an immediately repeated sequence");
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::wfc::wfc::rules::{WfcVector, END, START};
+
+    use super::Constraints;
+
+    fn get_vector(sets: &[&[&str]]) -> WfcVector {
+        sets.iter()
+            .map(|set| set.iter().map(|s| s.to_string()).collect())
+            .collect()
+    }
+
+    fn get_words(words: &[&str]) -> Vec<String> {
+        words.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_remove_banned_sanity() {
+        let constraints = Constraints {
+            banned: ["bad".to_string()].into_iter().collect(),
+            ..Default::default()
+        };
+        let mut vector = get_vector(&[&[START], &["bad", "good"], &["bad"], &[END]]);
+
+        constraints.remove_banned(&mut vector);
+
+        assert_eq!(vector, get_vector(&[&[START], &["good"], &["bad"], &[END]]));
+    }
+
+    #[test]
+    fn test_get_exhausted_sanity() {
+        let constraints = Constraints {
+            max_occurrences: Some(2),
+            ..Default::default()
+        };
+        let vector = get_vector(&[&[START], &["a"], &["b"], &["a"], &["a", "b"], &[END]]);
+
+        let exhausted = constraints.get_exhausted(&vector);
+
+        assert_eq!(exhausted.len(), 1);
+        assert!(exhausted.contains("a"));
+    }
+
+    #[test]
+    fn test_get_candidates_repetition() {
+        let constraints = Constraints {
+            no_repeat_ngram: Some(2),
+            ..Default::default()
+        };
+        let vector = get_vector(&[&[START], &["a"], &["b"], &["a"], &["a", "b", "c"], &[END]]);
+
+        let candidates = constraints.get_candidates(&vector, 4);
+
+        assert_eq!(candidates, vec!["c"]);
+    }
+
+    #[test]
+    fn test_get_candidates_required() {
+        let constraints = Constraints {
+            required: ["b".to_string(), "c".to_string()].into_iter().collect(),
+            ..Default::default()
+        };
+        let vector = get_vector(&[&[START], &["b"], &["a", "b", "c"], &[END]]);
+
+        let candidates = constraints.get_candidates(&vector, 2);
+
+        assert_eq!(candidates, vec!["c"]);
+    }
+
+    #[test]
+    fn test_get_candidates_reserves_required() {
+        let constraints = Constraints {
+            required: ["c".to_string()].into_iter().collect(),
+            ..Default::default()
+        };
+        let vector = get_vector(&[&[START], &["a", "b"], &["a", "c"], &[END]]);
+
+        assert_eq!(constraints.get_candidates(&vector, 1).len(), 2);
+        assert!(constraints.can_place_required(&vector));
+
+        let vector = get_vector(&[&[START], &["a", "b"], &["a"], &["b", "c"], &[END]]);
+        let collapsed = get_vector(&[&[START], &["a", "b"], &["a"], &["b"], &[END]]);
+
+        assert!(constraints.can_place_required(&vector));
+        assert!(!constraints.can_place_required(&collapsed));
+        assert!(constraints.get_candidates(&collapsed, 1).is_empty());
+    }
+
+    #[test]
+    fn test_check_sanity() {
+        let constraints = Constraints {
+            banned: ["bad".to_string()].into_iter().collect(),
+            required: ["good".to_string()].into_iter().collect(),
+            max_occurrences: Some(2),
+            no_repeat_ngram: Some(2),
+        };
+
+        assert_eq!(
+            constraints.check(&get_words(&[START, "good", "a", "b", "a", END])),
+            Ok(())
+        );
+        assert_eq!(
+            constraints.check(&get_words(&[START, "good", "bad", END])),
+            Err("The result contains a banned word")
+        );
+        assert_eq!(
+            constraints.check(&get_words(&[START, "a", END])),
+            Err("The result is missing a required word")
+        );
+        assert_eq!(
+            constraints.check(&get_words(&[START, "good", "a", "b", "a", "c", "a", END])),
+            Err("The result has a word appearing too many times")
+        );
+        assert_eq!(
+            constraints.check(&get_words(&[START, "good", "a", "b", "a", "b", END])),
+            Err("The result has an immediately repeated sequence")
+        );
+    }
+}
@@ -0,0 +1,171 @@
+use serde::Serialize;
+use std::collections::{BTreeSet, HashSet};
+
+use super::rules::{Allowed, Rules, END};
+
+/// The words allowed after a word in one rules but not the other
+#[derive(Serialize)]
+pub struct TransitionsDiff {
+    pub word: String,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+/// How much more (or less) likely a word became after a word
+#[derive(Serialize)]
+pub struct ProbabilityShift {
+    pub before: String,
+    pub after: String,
+    pub old: f64,
+    pub new: f64,
+}
+
+/// What changed between two rules, for comparing rules compiled from different corpora
+#[derive(Serialize)]
+pub struct RulesDiff {
+    pub added_tokens: Vec<String>,
+    pub removed_tokens: Vec<String>,
+    /// The words whose next words changed, including the added and removed words
+    pub transitions: Vec<TransitionsDiff>,
+    /// The biggest changes in the probability of the next word, only when both rules are weighted
+    pub shifts: Option<Vec<ProbabilityShift>>,
+}
+
+/// Returns the sorted words of the set which are not in the other set
+fn get_missing(set: &HashSet<String>, other: &HashSet<String>) -> Vec<String> {
+    let mut missing: Vec<String> = set.difference(other).cloned().collect();
+    missing.sort();
+    missing
+}
+
+/// Returns the probability of [after] following the word, not counting the end going back to
+/// the start
+fn get_probability(word: &str, allowed: Option<&Allowed>, after: &str) -> f64 {
+    let allowed = match allowed {
+        Some(allowed) if word != END => allowed,
+        _ => return 0.0,
+    };
+    let total = allowed.counts.values().sum::<usize>();
+    match total {
+        0 => 0.0,
+        total => allowed.counts.get(after).copied().unwrap_or(0) as f64 / total as f64,
+    }
+}
+
+/// Returns the [top] biggest changes in the probability of the next word of every word
+fn get_shifts(old: &Rules, new: &Rules, top: usize) -> Vec<ProbabilityShift> {
+    let words: BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+
+    let mut shifts: Vec<ProbabilityShift> = Vec::new();
+    for word in words.into_iter().filter(|word| *word != END) {
+        let (old_allowed, new_allowed) = (old.get(word), new.get(word));
+        let afters: BTreeSet<&String> = old_allowed
+            .into_iter()
+            .chain(new_allowed)
+            .flat_map(|allowed| allowed.counts.keys())
+            .collect();
+
+        for after in afters {
+            let shift = ProbabilityShift {
+                before: word.clone(),
+                after: after.clone(),
+                old: get_probability(word, old_allowed, after),
+                new: get_probability(word, new_allowed, after),
+            };
+            if shift.old != shift.new {
+                shifts.push(shift);
+            }
+        }
+    }
+
+    shifts.sort_by(|a, b| {
+        (b.new - b.old)
+            .abs()
+            .total_cmp(&(a.new - a.old).abs())
+            .then(a.before.cmp(&b.before))
+            .then(a.after.cmp(&b.after))
+    });
+    shifts.truncate(top);
+    shifts
+}
+
+/// Returns what changed from the old rules to the new ones, listing the [top] biggest probability
+/// shifts
+pub fn diff_rules(old: &Rules, new: &Rules, top: usize) -> RulesDiff {
+    let old_words: HashSet<String> = old.keys().cloned().collect();
+    let new_words: HashSet<String> = new.keys().cloned().collect();
+    let empty = Allowed::empty();
+
+    let mut words: Vec<&String> = old_words.union(&new_words).collect();
+    words.sort();
+    let transitions = words
+        .into_iter()
+        .map(|word| {
+            let old_after = &old.get(word).unwrap_or(&empty).after;
+            let new_after = &new.get(word).unwrap_or(&empty).after;
+            TransitionsDiff {
+                word: word.clone(),
+                added: get_missing(new_after, old_after),
+                removed: get_missing(old_after, new_after),
+            }
+        })
+        .filter(|diff| !diff.added.is_empty() || !diff.removed.is_empty())
+        .collect();
+
+    let is_weighted = |rules: &Rules| rules.values().any(|allowed| !allowed.counts.is_empty());
+    let shifts = (is_weighted(old) && is_weighted(new)).then(|| get_shifts(old, new, top));
+
+    RulesDiff {
+        added_tokens: get_missing(&new_words, &old_words),
+        removed_tokens: get_missing(&old_words, &new_words),
+        transitions,
+        shifts,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::wfc::parsing::text_parse::generate_rules;
+    use crate::wfc::wfc::rules::{END, START};
+
+    use super::diff_rules;
+
+    #[test]
+    fn test_diff_rules_sanity() {
+        let old = generate_rules("the cat sat. the cat ate".to_string());
+        let new = generate_rules("the cat sat. the dog sat".to_string());
+
+        let diff = diff_rules(&old, &new, 3);
+
+        assert_eq!(diff.added_tokens, vec!["dog"]);
+        assert_eq!(diff.removed_tokens, vec!["ate"]);
+
+        let words: Vec<&str> = diff
+            .transitions
+            .iter()
+            .map(|diff| diff.word.as_str())
+            .collect();
+        assert_eq!(words, vec!["ate", "cat", "dog", "sat", "the"]);
+        let the = diff.transitions.iter().find(|d| d.word == "the").unwrap();
+        assert_eq!(the.added, vec!["dog"]);
+        assert!(the.removed.is_empty());
+
+        let shifts = diff.shifts.unwrap();
+        assert_eq!(shifts.len(), 3);
+        assert_eq!(
+            (shifts[0].before.as_str(), shifts[0].after.as_str()),
+            ("ate", END)
+        );
+        assert_eq!((shifts[0].old, shifts[0].new), (1.0, 0.0));
+        assert_eq!(
+            (shifts[1].before.as_str(), shifts[1].after.as_str()),
+            ("dog", "sat")
+        );
+        assert_eq!(
+            (shifts[2].before.as_str(), shifts[2].after.as_str()),
+            ("cat", "ate")
+        );
+        assert_eq!((shifts[2].old, shifts[2].new), (0.5, 0.0));
+        assert!(shifts.iter().all(|shift| shift.before != START));
+    }
+}
@@ -0,0 +1,338 @@
+use super::pruning::get_reachable;
+use super::rules::{add_transition, Allowed, Rules, END, START};
+
+/// A change to a rules file, keeping the words before and after every word consistent
+#[derive(Debug, PartialEq, Eq)]
+pub enum Edit {
+    /// Allows [after] after [before], seen [count] times (once if the rules are weighted and
+    /// no count is given), adding the words which aren't in the rules only if [create] is set
+    AddTransition {
+        before: String,
+        after: String,
+        count: Option<usize>,
+        create: bool,
+    },
+    RemoveTransition {
+        before: String,
+        after: String,
+    },
+    /// Removes the word and every transition from or to it
+    RemoveToken(String),
+    RenameToken {
+        from: String,
+        to: String,
+    },
+    /// Replaces the words with a single one allowing every transition of them, adding up the
+    /// counts
+    MergeTokens {
+        words: Vec<String>,
+        into: String,
+    },
+}
+
+/// Returns wether the word is the start or the end, which can't be removed or renamed
+fn is_special(word: &str) -> bool {
+    word == START || word == END
+}
+
+/// Returns wether any transition of the rules has a count
+fn is_weighted(rules: &Rules) -> bool {
+    rules.values().any(|allowed| !allowed.counts.is_empty())
+}
+
+/// Forbids [after] after [before]
+fn remove_transition(rules: &mut Rules, before: &str, after: &str) -> Result<(), &'static str> {
+    let allowed = rules
+        .get_mut(before)
+        .filter(|allowed| allowed.after.contains(after))
+        .ok_or("The transition is not in the rules")?;
+    allowed.after.remove(after);
+    allowed.counts.remove(after);
+
+    if let Some(allowed) = rules.get_mut(after) {
+        allowed.before.remove(before);
+    }
+    Ok(())
+}
+
+/// Removes the word and every transition from or to it
+fn remove_token(rules: &mut Rules, word: &str) -> Result<(), &'static str> {
+    if is_special(word) {
+        return Err("The start and the end can't be changed");
+    }
+    let allowed = rules.remove(word).ok_or("The word is not in the rules")?;
+
+    for before in &allowed.before {
+        if let Some(before) = rules.get_mut(before) {
+            before.after.remove(word);
+            before.counts.remove(word);
+        }
+    }
+    for after in &allowed.after {
+        if let Some(after) = rules.get_mut(after) {
+            after.before.remove(word);
+        }
+    }
+    Ok(())
+}
+
+/// Replaces the words with [into], which can be one of them or a new word
+fn merge_tokens(rules: &mut Rules, words: &[String], into: &str) -> Result<(), &'static str> {
+    if words.iter().any(|word| is_special(word)) || is_special(into) {
+        return Err("The start and the end can't be changed");
+    }
+    if words.iter().any(|word| !rules.contains_key(word)) {
+        return Err("The word is not in the rules");
+    }
+    if rules.contains_key(into) && !words.iter().any(|word| word == into) {
+        return Err("The new word is already in the rules");
+    }
+
+    let map_word = |word: &String| -> String {
+        match words.contains(word) {
+            true => into.to_string(),
+            false => word.clone(),
+        }
+    };
+
+    let mut merged: Rules = rules
+        .keys()
+        .map(|word| (map_word(word), Allowed::empty()))
+        .collect();
+    for (word, allowed) in rules.iter() {
+        for after in &allowed.after {
+            let count = allowed.counts.get(after).copied().unwrap_or(0);
+            add_transition(&mut merged, &map_word(word), &map_word(after), count);
+        }
+    }
+    *rules = merged;
+
+    Ok(())
+}
+
+/// Applies the edit to the rules
+pub fn apply_edit(rules: &mut Rules, edit: &Edit) -> Result<(), &'static str> {
+    match edit {
+        Edit::AddTransition {
+            before,
+            after,
+            count,
+            create,
+        } => {
+            if !create && (!rules.contains_key(before) || !rules.contains_key(after)) {
+                return Err("The word is not in the rules");
+            }
+            let count = count.unwrap_or(match is_weighted(rules) {
+                true => 1,
+                false => 0,
+            });
+            add_transition(rules, before, after, count);
+            Ok(())
+        }
+        Edit::RemoveTransition { before, after } => remove_transition(rules, before, after),
+        Edit::RemoveToken(word) => remove_token(rules, word),
+        Edit::RenameToken { from, to } => {
+            if rules.contains_key(to) {
+                return Err("The new word is already in the rules");
+            }
+            merge_tokens(rules, std::slice::from_ref(from), to)
+        }
+        Edit::MergeTokens { words, into } => merge_tokens(rules, words, into),
+    }
+}
+
+/// Checks that every word allowed before or after a word allows it back, that every count is of
+/// an allowed word and that every word lies on a path from the start to the end
+pub fn validate_rules(rules: &Rules) -> Result<(), &'static str> {
+    if !rules.contains_key(START) || !rules.contains_key(END) {
+        return Err("The rules have no start or end");
+    }
+
+    let from_start = get_reachable(rules, START, true);
+    let to_end = get_reachable(rules, END, false);
+    let is_on_path =
+        |word: &String| is_special(word) || (from_start.contains(word) && to_end.contains(word));
+    if !rules.keys().all(is_on_path) {
+        return Err("A word doesn't lie on a path from the start to the end");
+    }
+
+    for (word, allowed) in rules {
+        for after in &allowed.after {
+            match rules.get(after) {
+                Some(after) if after.before.contains(word) => (),
+                _ => return Err("A word allowed after a word doesn't allow it before"),
+            }
+        }
+        for before in &allowed.before {
+            match rules.get(before) {
+                Some(before) if before.after.contains(word) => (),
+                _ => return Err("A word allowed before a word doesn't allow it after"),
+            }
+        }
+        if allowed
+            .counts
+            .keys()
+            .any(|after| !allowed.after.contains(after))
+        {
+            return Err("A word has a count for a word which is not allowed after it");
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::wfc::parsing::text_parse::generate_rules;
+    use crate::wfc::wfc::rules::{Rules, END, START};
+
+    use super::{apply_edit, validate_rules, Edit};
+
+    fn get_rules() -> Rules {
+        generate_rules("the cat sat. the dog sat. a cat ate".to_string())
+    }
+
+    #[test]
+    fn test_apply_edit_transitions() {
+        let mut rules = get_rules();
+
+        let edit = Edit::AddTransition {
+            before: "dog".to_string(),
+            after: "ate".to_string(),
+            count: None,
+            create: false,
+        };
+        apply_edit(&mut rules, &edit).unwrap();
+        assert!(rules["dog"].after.contains("ate"));
+        assert!(rules["ate"].before.contains("dog"));
+        assert_eq!(rules["dog"].counts["ate"], 1);
+
+        let edit = Edit::RemoveTransition {
+            before: "the".to_string(),
+            after: "cat".to_string(),
+        };
+        apply_edit(&mut rules, &edit).unwrap();
+        assert!(!rules["the"].after.contains("cat"));
+        assert!(!rules["the"].counts.contains_key("cat"));
+        assert!(!rules["cat"].before.contains("the"));
+        assert_eq!(
+            apply_edit(&mut rules, &edit),
+            Err("The transition is not in the rules")
+        );
+        assert_eq!(validate_rules(&rules), Ok(()));
+    }
+
+    #[test]
+    fn test_apply_edit_remove_token() {
+        let mut rules = get_rules();
+
+        apply_edit(&mut rules, &Edit::RemoveToken("dog".to_string())).unwrap();
+
+        assert!(!rules.contains_key("dog"));
+        assert!(!rules["the"].after.contains("dog"));
+        assert!(!rules["sat"].before.contains("dog"));
+        assert_eq!(validate_rules(&rules), Ok(()));
+        assert_eq!(
+            apply_edit(&mut rules, &Edit::RemoveToken(START.to_string())),
+            Err("The start and the end can't be changed")
+        );
+    }
+
+    #[test]
+    fn test_apply_edit_rename_token() {
+        let mut rules = get_rules();
+        let edit = Edit::RenameToken {
+            from: "cat".to_string(),
+            to: "kitten".to_string(),
+        };
+
+        apply_edit(&mut rules, &edit).unwrap();
+
+        assert!(!rules.contains_key("cat"));
+        assert_eq!(rules["the"].counts["kitten"], 1);
+        assert!(rules["kitten"].after.contains("ate"));
+        assert!(rules["ate"].before.contains("kitten"));
+        assert_eq!(validate_rules(&rules), Ok(()));
+
+        let edit = Edit::RenameToken {
+            from: "kitten".to_string(),
+            to: "dog".to_string(),
+        };
+        assert_eq!(
+            apply_edit(&mut rules, &edit),
+            Err("The new word is already in the rules")
+        );
+    }
+
+    #[test]
+    fn test_apply_edit_merge_tokens() {
+        let mut rules = generate_rules("the cat dog sat. the dog ate".to_string());
+        let edit = Edit::MergeTokens {
+            words: vec!["cat".to_string(), "dog".to_string()],
+            into: "animal".to_string(),
+        };
+
+        apply_edit(&mut rules, &edit).unwrap();
+
+        assert!(!rules.contains_key("cat") && !rules.contains_key("dog"));
+        assert_eq!(rules["the"].counts["animal"], 2);
+        assert_eq!(rules["animal"].counts["animal"], 1);
+        assert!(rules["animal"].after.contains("sat"));
+        assert!(rules["animal"].after.contains("ate"));
+        assert!(rules[END].after.contains(START));
+        assert_eq!(validate_rules(&rules), Ok(()));
+    }
+
+    #[test]
+    fn test_validate_rules_sanity() {
+        let mut rules = get_rules();
+        assert_eq!(validate_rules(&rules), Ok(()));
+
+        rules.get_mut("cat").unwrap().before.remove("the");
+        assert_eq!(
+            validate_rules(&rules),
+            Err("A word allowed after a word doesn't allow it before")
+        );
+    }
+
+    #[test]
+    fn test_validate_rules_unreachable() {
+        let mut rules = get_rules();
+
+        let edit = Edit::RemoveTransition {
+            before: "cat".to_string(),
+            after: "ate".to_string(),
+        };
+        apply_edit(&mut rules, &edit).unwrap();
+        assert_eq!(
+            validate_rules(&rules),
+            Err("A word doesn't lie on a path from the start to the end")
+        );
+    }
+
+    #[test]
+    fn test_apply_edit_add_transition_create() {
+        let mut rules = get_rules();
+        let add = |before: &str, after: &str, create: bool| Edit::AddTransition {
+            before: before.to_string(),
+            after: after.to_string(),
+            count: None,
+            create,
+        };
+
+        assert_eq!(
+            apply_edit(&mut rules, &add("the", "kitten", false)),
+            Err("The word is not in the rules")
+        );
+        assert!(!rules.contains_key("kitten"));
+
+        apply_edit(&mut rules, &add("the", "kitten", true)).unwrap();
+        assert_eq!(
+            validate_rules(&rules),
+            Err("A word doesn't lie on a path from the start to the end")
+        );
+        apply_edit(&mut rules, &add("kitten", "sat", false)).unwrap();
+        assert!(rules["sat"].before.contains("kitten"));
+        assert_eq!(validate_rules(&rules), Ok(()));
+    }
+}
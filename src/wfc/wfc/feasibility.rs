@@ -0,0 +1,139 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+use super::rules::{CompiledRules, END};
+
+/// How many steps are walked at most looking for the lengths to repeat, lengths after that are
+/// assumed to be feasible
+const MAX_STEPS: usize = 10_000;
+
+/// The numbers of tokens which can lie between a word and the end. The words reachable in a number
+/// of steps only depend on the words reachable in one step less, so they eventually repeat and the
+/// feasible lengths become periodic.
+pub struct Feasibility {
+    /// Wether every length is feasible, up to where they repeat (or the steps run out)
+    feasible: Vec<bool>,
+    /// The length from which the feasible lengths repeat, and every how many lengths
+    cycle: Option<(usize, usize)>,
+}
+
+/// Returns the words reachable from the word in [steps] steps
+fn walk(compiled_rules: &CompiledRules, from: &str, steps: usize) -> Vec<bool> {
+    let mut reachable = vec![false; compiled_rules.words.len()];
+    if let Some(id) = compiled_rules.ids.get(from) {
+        reachable[*id] = true;
+    }
+    for _ in 0..steps {
+        reachable = step(compiled_rules, &reachable);
+    }
+    reachable
+}
+
+/// Returns the words reachable in one step from the reachable words
+fn step(compiled_rules: &CompiledRules, reachable: &[bool]) -> Vec<bool> {
+    let mut next = vec![false; reachable.len()];
+    for (id, alive) in reachable.iter().enumerate() {
+        if *alive {
+            for after in &compiled_rules.after[id] {
+                next[*after] = true;
+            }
+        }
+    }
+    next
+}
+
+/// Returns the hash of the reachable words
+fn get_hash(reachable: &[bool]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    reachable.hash(&mut hasher);
+    hasher.finish()
+}
+
+impl Feasibility {
+    /// Walks the rules from the word (the start for a whole text) until the words reachable repeat
+    pub fn new(compiled_rules: &CompiledRules, from: &str) -> Self {
+        let end = compiled_rules.ids.get(END).copied();
+        let mut reachable = walk(compiled_rules, from, 0);
+
+        // Only the hashes of the reachable words are kept, a hash seen again being checked by
+        // walking to its step again
+        let mut seen = HashMap::<u64, usize>::new();
+        let mut feasible = Vec::<bool>::new();
+        let mut cycle = None;
+        // After [index] steps, the tokens between the word and the end are one less
+        for index in 0..MAX_STEPS {
+            if let Some(previous) = seen.insert(get_hash(&reachable), index) {
+                if walk(compiled_rules, from, previous) == reachable {
+                    cycle = Some((previous.saturating_sub(1), index - previous));
+                    break;
+                }
+            }
+            if index > 0 {
+                feasible.push(end.is_some_and(|end| reachable[end]));
+            }
+            reachable = step(compiled_rules, &reachable);
+        }
+
+        Self { feasible, cycle }
+    }
+
+    /// Returns wether any text of [length] tokens between the word and the end fits the rules
+    pub fn is_feasible(&self, length: usize) -> bool {
+        match (self.feasible.get(length), self.cycle) {
+            (Some(feasible), _) => *feasible,
+            (None, Some((start, period))) => {
+                let index = start + (length - start) % period;
+                self.feasible.get(index).copied().unwrap_or(false)
+            }
+            (None, None) => true,
+        }
+    }
+
+    /// Returns the feasible lengths closest to [length], the longest shorter one and the shortest
+    /// longer one
+    pub fn get_nearest(&self, length: usize) -> (Option<usize>, Option<usize>) {
+        let shorter = (0..length).rev().find(|length| self.is_feasible(*length));
+        let limit = match self.cycle {
+            Some((start, period)) => start.max(length) + period,
+            None => self.feasible.len().max(length) + 1,
+        };
+        let longer = (length + 1..=limit).find(|length| self.is_feasible(*length));
+        (shorter, longer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::wfc::parsing::text_parse::generate_rules;
+    use crate::wfc::wfc::rules::{CompiledRules, START};
+
+    use super::Feasibility;
+
+    #[test]
+    fn test_feasibility_sanity() {
+        let rules = generate_rules("the cat sat".to_string());
+        let feasibility = Feasibility::new(&CompiledRules::new(&rules), START);
+
+        let feasible: Vec<usize> = (0..12)
+            .filter(|length| feasibility.is_feasible(*length))
+            .collect();
+        // the cat sat, then again after the end and the start
+        assert_eq!(feasible, vec![3, 8]);
+        assert!(feasibility.is_feasible(13));
+        assert!(!feasibility.is_feasible(14));
+        assert_eq!(feasibility.get_nearest(5), (Some(3), Some(8)));
+        assert_eq!(feasibility.get_nearest(2), (None, Some(3)));
+    }
+
+    #[test]
+    fn test_feasibility_from_word() {
+        let rules = generate_rules("the cat sat. the dog".to_string());
+        let feasibility = Feasibility::new(&CompiledRules::new(&rules), "cat");
+
+        assert!(!feasibility.is_feasible(0));
+        assert!(feasibility.is_feasible(4));
+        assert!(!feasibility.is_feasible(5));
+        assert_eq!(feasibility.get_nearest(5), (Some(4), Some(8)));
+    }
+}
@@ -0,0 +1,836 @@
+use std::collections::{BTreeMap, HashMap};
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
+use rayon::ThreadPoolBuilder;
+
+use crate::wfc::parsing::post_processing::merge;
+use crate::wfc::parsing::template::{template_to_wfc_vector, TemplatePart};
+
+use super::algorithm::{iterate_with, propagate_all, verify, Heuristic};
+use super::classes::{get_class, members_to_class_map, sample_members, ClassMap, Members};
+use super::constraints::Constraints;
+use super::rules::{generate_wfc_vector, CompiledRules, Rules, WfcVector, END, START};
+use super::sampler::{count_texts, sample_with, Sampler};
+
+/// The number of tokens generated when no length is given
+const DEFAULT_LENGTH: usize = 100;
+/// How many times a template is filled before giving up on filling it without contradictions
+const TEMPLATE_ATTEMPTS: usize = 20;
+/// How many texts the exact samplers draw before giving up on one meeting the constraints
+const SAMPLE_ATTEMPTS: usize = 20;
+
+/// Moves the lengths of the blanks to the next combination within their ranges, returning false
+/// once every combination was gone through
+fn next_lengths(lengths: &mut [usize], ranges: &[(usize, usize)]) -> bool {
+    for (length, (min, max)) in lengths.iter_mut().zip(ranges) {
+        if *length < *max {
+            *length += 1;
+            return true;
+        }
+        *length = *min;
+    }
+    false
+}
+
+/// Generates texts from rules, see [Generator::from_rules]
+pub struct Generator {
+    rules: Rules,
+    /// The rules compiled once, shared by every generated text
+    compiled_rules: CompiledRules,
+    length: usize,
+    constraints: Constraints,
+    heuristic: Heuristic,
+    sampler: Sampler,
+    rng: StdRng,
+    /// The words some tokens must be, by index (the start being 0)
+    pins: BTreeMap<usize, String>,
+    /// The first tokens of every text, which are not counted in the length
+    prefix: Vec<String>,
+    /// The words of every class when the rules are between classes, without the banned words
+    members: Option<Members>,
+    /// The class of every word when the rules are between classes, empty otherwise
+    classes: ClassMap,
+    /// The constraints on the words, checked again once the classes are replaced with their words
+    word_constraints: Constraints,
+}
+
+/// Returns the constraints between the classes of the words, and the members without the banned
+/// words. A class is only banned once every word of it is, a required word requires its class.
+fn classify_constraints(
+    constraints: &Constraints,
+    mut members: Members,
+    classes: &ClassMap,
+) -> (Constraints, Members) {
+    for words in members.values_mut() {
+        words.retain(|word, _| !constraints.banned.contains(word));
+    }
+    let banned = constraints
+        .banned
+        .iter()
+        .map(|word| get_class(word, classes).to_string())
+        .filter(|class| members.get(class).is_none_or(HashMap::is_empty))
+        .collect();
+    let required = constraints
+        .required
+        .iter()
+        .map(|word| get_class(word, classes).to_string())
+        .collect();
+
+    let constraints = Constraints {
+        banned,
+        required,
+        ..constraints.clone()
+    };
+    (constraints, members)
+}
+
+/// Configures a [Generator] before building it
+pub struct GeneratorBuilder {
+    rules: Rules,
+    length: usize,
+    seed: Option<u64>,
+    constraints: Constraints,
+    heuristic: Heuristic,
+    sampler: Sampler,
+    members: Option<Members>,
+}
+
+/// Returns the tokens starting a text which starts with the prefix
+pub fn with_start(prefix: &[String]) -> Vec<String> {
+    let mut tokens = vec![START.to_string()];
+    tokens.extend(prefix.iter().cloned());
+    tokens
+}
+
+impl GeneratorBuilder {
+    /// Sets the number of tokens to generate, not counting the start and the end
+    pub fn length(mut self, length: usize) -> Self {
+        self.length = length;
+        self
+    }
+
+    /// Sets the seed of the random generator, making the generated texts reproducible
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Sets how the next set to collapse is chosen
+    pub fn heuristic(mut self, heuristic: Heuristic) -> Self {
+        self.heuristic = heuristic;
+        self
+    }
+
+    /// Sets how the words of the texts are chosen
+    pub fn sampler(mut self, sampler: Sampler) -> Self {
+        self.sampler = sampler;
+        self
+    }
+
+    /// Sets the global constraints enforced on the generated texts
+    pub fn constraints(mut self, constraints: Constraints) -> Self {
+        self.constraints = constraints;
+        self
+    }
+
+    /// Sets the words of every class, for rules between classes. Every class generated is
+    /// replaced with one of its words, the words of the prefix, the pins, the templates and the
+    /// constraints being replaced with their classes while generating.
+    pub fn members(mut self, members: Members) -> Self {
+        self.members = Some(members);
+        self
+    }
+
+    /// Builds the generator, failing if the rules can't start or end a text
+    pub fn build(self) -> Result<Generator, &'static str> {
+        if !self.rules.contains_key(START) || !self.rules.contains_key(END) {
+            return Err("The rules have no start or end");
+        }
+
+        let rng = match self.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+
+        let classes = self
+            .members
+            .as_ref()
+            .map(members_to_class_map)
+            .unwrap_or_default();
+        let (constraints, members) = match self.members {
+            Some(members) => {
+                let (constraints, members) =
+                    classify_constraints(&self.constraints, members, &classes);
+                (constraints, Some(members))
+            }
+            None => (self.constraints.clone(), None),
+        };
+
+        Ok(Generator {
+            compiled_rules: CompiledRules::new(&self.rules),
+            rules: self.rules,
+            length: self.length,
+            constraints,
+            heuristic: self.heuristic,
+            sampler: self.sampler,
+            rng,
+            pins: BTreeMap::new(),
+            prefix: Vec::new(),
+            members,
+            classes,
+            word_constraints: self.constraints,
+        })
+    }
+}
+
+impl Generator {
+    /// Starts configuring a generator using the given rules
+    pub fn from_rules(rules: Rules) -> GeneratorBuilder {
+        GeneratorBuilder {
+            rules,
+            length: DEFAULT_LENGTH,
+            seed: None,
+            constraints: Constraints::default(),
+            heuristic: Heuristic::default(),
+            sampler: Sampler::default(),
+            members: None,
+        }
+    }
+
+    /// Returns the rules the generator uses
+    pub fn rules(&self) -> &Rules {
+        &self.rules
+    }
+
+    /// Returns the rules the generator uses, compiled
+    pub fn compiled_rules(&self) -> &CompiledRules {
+        &self.compiled_rules
+    }
+
+    /// Returns the first tokens of every text
+    pub fn prefix(&self) -> &[String] {
+        &self.prefix
+    }
+
+    /// Returns the token of the word in the rules, its class if the rules are between classes
+    pub fn get_token<'a>(&'a self, word: &'a str) -> &'a str {
+        get_class(word, &self.classes)
+    }
+
+    /// Sets the number of tokens to generate, not counting the start and the end
+    pub fn set_length(&mut self, length: usize) {
+        self.length = length;
+    }
+
+    /// Restarts the random generator from the seed
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    /// Forces the token at the index (the start being 0) to be the word
+    pub fn pin(&mut self, index: usize, word: &str) -> Result<(), &'static str> {
+        if !self.rules.contains_key(self.get_token(word)) {
+            return Err("The word is not in the rules");
+        }
+        self.pins.insert(index, word.to_string());
+        Ok(())
+    }
+
+    /// Removes the pin of the token at the index, returning wether there was one
+    pub fn unpin(&mut self, index: usize) -> bool {
+        self.pins.remove(&index).is_some()
+    }
+
+    /// Starts every text with the tokens, generating the length in tokens after them. The rules
+    /// must allow every word of the prefix after the previous one.
+    pub fn set_prefix(&mut self, prefix: Vec<String>) -> Result<(), &'static str> {
+        let tokens: Vec<String> = prefix
+            .iter()
+            .map(|word| self.get_token(word).to_string())
+            .collect();
+        if tokens.iter().any(|token| !self.rules.contains_key(token)) {
+            return Err("The word is not in the rules");
+        }
+        if !verify(&with_start(&tokens), &self.rules).is_empty() {
+            return Err("The prefix has adjacent words which the rules don't allow");
+        }
+        self.prefix = prefix;
+        Ok(())
+    }
+
+    /// Returns the pinned words by index
+    pub fn pins(&self) -> &BTreeMap<usize, String> {
+        &self.pins
+    }
+
+    /// Returns the vector to collapse, with the pinned tokens collapsed
+    fn get_vector(&self) -> Result<WfcVector, &'static str> {
+        let length = self.prefix.len() + self.length;
+        let mut vector = generate_wfc_vector(&self.rules, length);
+        for (index, word) in self.get_literals() {
+            if index == 0 || index > length {
+                return Err("A pinned token is out of range");
+            }
+            vector[index] = [self.get_token(&word).to_string()].into_iter().collect();
+        }
+        Ok(vector)
+    }
+
+    /// Returns the words of the prefix and the pins by index, which are kept as they are when the
+    /// classes are replaced with their words
+    fn get_literals(&self) -> Vec<(usize, String)> {
+        let prefix = (1..).zip(self.prefix.iter().cloned());
+        let pins = self.pins.iter().map(|(index, word)| (*index, word.clone()));
+        prefix.chain(pins).collect()
+    }
+
+    /// Returns the words every token can still be before anything is collapsed, after the pins and
+    /// banned words were propagated
+    pub fn domains(&self) -> Result<WfcVector, &'static str> {
+        let mut vector = self.get_vector()?;
+        self.constraints.remove_banned(&mut vector);
+
+        propagate_all(&mut vector, &self.compiled_rules)?;
+        Ok(vector)
+    }
+
+    /// Returns how many texts fit the rules, the pins and the banned words, or None if there are
+    /// more than fit in a u128
+    pub fn count(&self) -> Result<Option<u128>, &'static str> {
+        let mut vector = self.get_vector()?;
+        self.constraints.remove_banned(&mut vector);
+        Ok(count_texts(&vector, &self.compiled_rules))
+    }
+
+    /// Collapses the vector with the given random generator, or draws a text fitting it with the
+    /// exact samplers (which only enforce the banned words of the constraints while drawing, so
+    /// texts are drawn again until one meets the rest of them)
+    fn iterate(
+        &self,
+        mut vector: WfcVector,
+        rng: &mut StdRng,
+    ) -> Result<Vec<String>, &'static str> {
+        if self.sampler == Sampler::Wfc {
+            return iterate_with(
+                vector,
+                &self.rules,
+                &self.compiled_rules,
+                &self.constraints,
+                self.heuristic,
+                rng,
+            );
+        }
+
+        self.constraints.remove_banned(&mut vector);
+        let weighted = self.sampler == Sampler::Weighted;
+        let mut error = "No text of this length fits the rules";
+        for _ in 0..SAMPLE_ATTEMPTS {
+            let result = sample_with(&vector, &self.rules, &self.compiled_rules, weighted, rng)?;
+            match self.constraints.check(&result) {
+                Ok(()) => return Ok(result),
+                Err(e) => error = e,
+            }
+        }
+        Err(error)
+    }
+
+    /// Replaces the classes of the tokens with their words if the rules are between classes,
+    /// keeping the literal words at their index and putting the required words missing in place
+    /// of a token of their class, then checks the banned and required words again
+    fn sample_members(
+        &self,
+        tokens: Vec<String>,
+        literals: &[(usize, String)],
+        rng: &mut StdRng,
+    ) -> Result<Vec<String>, &'static str> {
+        let members = match &self.members {
+            Some(members) => members,
+            None => return Ok(tokens),
+        };
+
+        let mut words = sample_members(tokens.clone(), members, rng);
+        for (index, word) in literals {
+            words[*index] = word.clone();
+        }
+
+        let mut required: Vec<&String> = self.word_constraints.required.iter().collect();
+        required.sort();
+        for word in required {
+            if words.contains(word) {
+                continue;
+            }
+            let class = self.get_token(word);
+            let free: Vec<usize> = (0..tokens.len())
+                .filter(|i| tokens[*i] == class)
+                .filter(|i| literals.iter().all(|(index, _)| index != i))
+                .filter(|i| !self.word_constraints.required.contains(&words[*i]))
+                .collect();
+            if let Some(index) = free.choose(rng) {
+                words[*index] = word.clone();
+            }
+        }
+
+        self.word_constraints.check_words(&words)?;
+        Ok(words)
+    }
+
+    /// Generates the tokens of a text with the given random generator
+    fn generate_tokens_with(&self, rng: &mut StdRng) -> Result<Vec<String>, &'static str> {
+        let tokens = self.iterate(self.get_vector()?, rng)?;
+        self.sample_members(tokens, &self.get_literals(), rng)
+    }
+
+    /// Returns the template with its words replaced with their classes, and its words by index
+    /// when the blanks have the given lengths
+    fn classify_template(
+        &self,
+        parts: &[TemplatePart],
+        lengths: &[usize],
+    ) -> (Vec<TemplatePart>, Vec<(usize, String)>) {
+        let mut class_parts = Vec::<TemplatePart>::new();
+        let mut literals = Vec::<(usize, String)>::new();
+        let mut lengths = lengths.iter();
+        let mut index = 1;
+
+        for part in parts {
+            match part {
+                TemplatePart::Word(word) => {
+                    class_parts.push(TemplatePart::Word(self.get_token(word).to_string()));
+                    literals.push((index, word.clone()));
+                    index += 1;
+                }
+                TemplatePart::Blank { min, max } => {
+                    class_parts.push(TemplatePart::Blank {
+                        min: *min,
+                        max: *max,
+                    });
+                    index += lengths.next().copied().unwrap_or(0);
+                }
+            }
+        }
+
+        (class_parts, literals)
+    }
+
+    /// Fills the blanks of a template with the given lengths, returning None if the filled
+    /// template has contradictions
+    fn fill_template(
+        &self,
+        parts: &[TemplatePart],
+        lengths: &[usize],
+        rng: &mut StdRng,
+    ) -> Result<Option<Vec<String>>, &'static str> {
+        let (class_parts, literals) = self.classify_template(parts, lengths);
+        let vector = template_to_wfc_vector(&class_parts, lengths, &self.rules)?;
+
+        match self.iterate(vector, rng) {
+            Ok(tokens) if verify(&tokens, &self.rules).is_empty() => {
+                Ok(Some(self.sample_members(tokens, &literals, rng)?))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Fills the blanks of a template with the given random generator, drawing the lengths of the
+    /// variable blanks a few times and then trying every combination of them in order, failing if
+    /// none of them fills the template without contradictions
+    fn generate_template_with(
+        &self,
+        parts: &[TemplatePart],
+        rng: &mut StdRng,
+    ) -> Result<Vec<String>, &'static str> {
+        let ranges: Vec<(usize, usize)> = parts
+            .iter()
+            .filter_map(|part| match part {
+                TemplatePart::Blank { min, max } => Some((*min, *max)),
+                TemplatePart::Word(_) => None,
+            })
+            .collect();
+
+        for _ in 0..TEMPLATE_ATTEMPTS {
+            let lengths: Vec<usize> = ranges
+                .iter()
+                .map(|(min, max)| rng.gen_range(*min..=*max))
+                .collect();
+            if let Some(tokens) = self.fill_template(parts, &lengths, rng)? {
+                return Ok(tokens);
+            }
+        }
+
+        // Lengths which no text fits fail right after the first propagation
+        let mut lengths: Vec<usize> = ranges.iter().map(|(min, _)| *min).collect();
+        loop {
+            if let Some(tokens) = self.fill_template(parts, &lengths, rng)? {
+                return Ok(tokens);
+            }
+            if !next_lengths(&mut lengths, &ranges) {
+                return Err("The template could not be filled");
+            }
+        }
+    }
+
+    /// Generates the tokens of a text, including the start and the end
+    pub fn generate_tokens(&mut self) -> Result<Vec<String>, &'static str> {
+        let mut rng = self.rng.clone();
+        let result = self.generate_tokens_with(&mut rng);
+        self.rng = rng;
+        result
+    }
+
+    /// Fills the blanks of a template, see [parse_template](crate::wfc::parsing::template::parse_template)
+    pub fn generate_template(
+        &mut self,
+        parts: &[TemplatePart],
+    ) -> Result<Vec<String>, &'static str> {
+        let mut rng = self.rng.clone();
+        let result = self.generate_template_with(parts, &mut rng);
+        self.rng = rng;
+        result
+    }
+
+    /// Generates [count] texts (or fills the template [count] times) in parallel on [jobs] threads,
+    /// or as many as there are cores for 0. Every text has its own random generator seeded from
+    /// this one, so the texts don't depend on the number of threads.
+    pub fn generate_batch(
+        &mut self,
+        count: usize,
+        jobs: usize,
+        template: Option<&[TemplatePart]>,
+    ) -> Result<Vec<Vec<String>>, &'static str> {
+        let seeds: Vec<u64> = (0..count).map(|_| self.rng.gen()).collect();
+        let pool = ThreadPoolBuilder::new()
+            .num_threads(jobs)
+            .build()
+            .map_err(|_| "The threads could not be started")?;
+
+        pool.install(|| {
+            seeds
+                .into_par_iter()
+                .map(|seed| {
+                    let mut rng = StdRng::seed_from_u64(seed);
+                    match template {
+                        Some(parts) => self.generate_template_with(parts, &mut rng),
+                        None => self.generate_tokens_with(&mut rng),
+                    }
+                })
+                .collect()
+        })
+    }
+
+    /// Generates a text, merging the tokens back together
+    pub fn generate(&mut self) -> Result<String, &'static str> {
+        Ok(merge(self.generate_tokens()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::wfc::parsing::template::parse_template;
+    use crate::wfc::parsing::text_parse::generate_rules;
+    use crate::wfc::wfc::algorithm::{verify, Heuristic};
+    use crate::wfc::wfc::classes::{classify_rules, get_class, ClassMap};
+    use crate::wfc::wfc::constraints::Constraints;
+    use crate::wfc::wfc::rules::{Rules, END, START};
+    use crate::wfc::wfc::sampler::Sampler;
+
+    use super::Generator;
+
+    fn get_rules() -> Rules {
+        generate_rules(
+            "the cat sat on the mat. the dog sat on the cat. a cat ate the dog.".to_string(),
+        )
+    }
+
+    #[test]
+    fn test_generator_sanity() {
+        let mut generator = Generator::from_rules(get_rules())
+            .length(6)
+            .build()
+            .unwrap();
+
+        let tokens = generator.generate_tokens().unwrap();
+
+        assert_eq!(tokens.len(), 8);
+        assert_eq!(tokens[0], START);
+        assert_eq!(tokens[7], END);
+    }
+
+    #[test]
+    fn test_generator_seed() {
+        for heuristic in [
+            Heuristic::MinimumEntropy,
+            Heuristic::LeftToRight,
+            Heuristic::Random,
+        ] {
+            let generate = || {
+                let mut generator = Generator::from_rules(get_rules())
+                    .length(12)
+                    .seed(42)
+                    .heuristic(heuristic)
+                    .build()
+                    .unwrap();
+                [generator.generate().unwrap(), generator.generate().unwrap()]
+            };
+
+            assert_eq!(generate(), generate());
+        }
+    }
+
+    #[test]
+    fn test_generator_generate_batch() {
+        let generate = |jobs| {
+            let mut generator = Generator::from_rules(get_rules())
+                .length(10)
+                .seed(7)
+                .build()
+                .unwrap();
+            generator.generate_batch(20, jobs, None).unwrap()
+        };
+
+        let batch = generate(1);
+        assert_eq!(batch.len(), 20);
+        assert!(batch.iter().all(|tokens| tokens.len() == 12));
+        assert_eq!(batch, generate(4));
+    }
+
+    #[test]
+    fn test_generator_invalid_rules() {
+        assert_eq!(
+            Generator::from_rules(Rules::new()).build().err(),
+            Some("The rules have no start or end")
+        );
+    }
+
+    #[test]
+    fn test_generator_pin() {
+        let mut generator = Generator::from_rules(get_rules())
+            .length(5)
+            .build()
+            .unwrap();
+
+        generator.pin(2, "dog").unwrap();
+        assert_eq!(
+            generator.generate_tokens(),
+            Err("No text of this length fits the rules")
+        );
+        generator.set_length(6);
+        for _ in 0..10 {
+            assert_eq!(generator.generate_tokens().unwrap()[2], "dog");
+        }
+        assert!(generator.unpin(2));
+        assert!(!generator.unpin(2));
+
+        assert_eq!(
+            generator.pin(1, "bird"),
+            Err("The word is not in the rules")
+        );
+        generator.set_length(5);
+        generator.pin(6, "dog").unwrap();
+        assert_eq!(
+            generator.generate_tokens(),
+            Err("A pinned token is out of range")
+        );
+    }
+
+    #[test]
+    fn test_generator_prefix() {
+        let mut generator = Generator::from_rules(get_rules())
+            .length(3)
+            .build()
+            .unwrap();
+        let prefix = ["the", "dog", "sat", "on"].map(|s| s.to_string()).to_vec();
+
+        generator.set_prefix(prefix.clone()).unwrap();
+        let tokens = generator.generate_tokens().unwrap();
+
+        assert_eq!(tokens.len(), 9);
+        assert_eq!(tokens[1..5], prefix);
+        assert_eq!(tokens[5], "the");
+        assert_eq!(tokens[7], ".");
+        assert_eq!(
+            generator.set_prefix(vec!["bird".to_string()]),
+            Err("The word is not in the rules")
+        );
+        assert_eq!(
+            generator.set_prefix(vec!["the".to_string(), "on".to_string()]),
+            Err("The prefix has adjacent words which the rules don't allow")
+        );
+    }
+
+    #[test]
+    fn test_generator_generate_template() {
+        let mut generator = Generator::from_rules(get_rules()).build().unwrap();
+        let parts = parse_template("the ___ sat on ___{1,3}.").unwrap();
+
+        for _ in 0..10 {
+            let tokens = generator.generate_template(&parts).unwrap();
+
+            assert!(verify(&tokens, generator.rules()).is_empty());
+            assert_eq!(tokens[1], "the");
+            assert_eq!(tokens[3], "sat");
+            assert_eq!(tokens[tokens.len() - 2], ".");
+        }
+
+        let parts = parse_template("the ___ the").unwrap();
+        assert_eq!(
+            generator.generate_template(&parts),
+            Err("The template could not be filled")
+        );
+    }
+
+    #[test]
+    fn test_generator_generate_template_single_length() {
+        let text = "the a b c d e f g h i j k l m n o p q r s sat .";
+        let mut generator = Generator::from_rules(generate_rules(text.to_string()))
+            .seed(1)
+            .build()
+            .unwrap();
+        // Only a blank of 19 words fits
+        let parts = parse_template("the ___{0,22} sat .").unwrap();
+
+        let tokens = generator.generate_template(&parts).unwrap();
+
+        assert_eq!(tokens.len(), 24);
+        assert_eq!(tokens[20], "s");
+    }
+
+    #[test]
+    fn test_generator_domains() {
+        let mut generator = Generator::from_rules(get_rules())
+            .length(3)
+            .build()
+            .unwrap();
+        // a never starts a text
+        generator.pin(1, "a").unwrap();
+        assert_eq!(
+            generator.domains(),
+            Err("No text of this length fits the rules")
+        );
+        generator.unpin(1);
+        generator.pin(2, "dog").unwrap();
+
+        let domains = generator.domains().unwrap();
+
+        assert_eq!(domains.len(), 5);
+        assert_eq!(domains[1].len(), 1);
+        assert!(domains[1].contains("the"));
+        assert_eq!(domains[3].len(), 1);
+        assert!(domains[3].contains("."));
+    }
+
+    #[test]
+    fn test_generator_members() {
+        let rules = get_rules();
+        let classes: ClassMap = ["cat", "dog", "mat"]
+            .into_iter()
+            .map(|word| (word.to_string(), "NOUN".to_string()))
+            .collect();
+        let (class_rules, members) = classify_rules(&rules, &classes).unwrap();
+        let mut generator = Generator::from_rules(class_rules)
+            .length(10)
+            .members(members)
+            .build()
+            .unwrap();
+
+        let tokens = generator.generate_tokens().unwrap();
+
+        assert!(tokens.iter().all(|token| rules.contains_key(token)));
+        let tokens: Vec<String> = tokens
+            .iter()
+            .map(|token| get_class(token, &classes).to_string())
+            .collect();
+        assert!(verify(&tokens, generator.rules()).is_empty());
+    }
+
+    #[test]
+    fn test_generator_members_words() {
+        let rules = get_rules();
+        let classes: ClassMap = ["cat", "dog", "mat"]
+            .into_iter()
+            .map(|word| (word.to_string(), "NOUN".to_string()))
+            .collect();
+        let (class_rules, members) = classify_rules(&rules, &classes).unwrap();
+        let constraints = Constraints {
+            banned: ["mat".to_string()].into_iter().collect(),
+            required: ["dog".to_string()].into_iter().collect(),
+            ..Default::default()
+        };
+        let mut generator = Generator::from_rules(class_rules)
+            .length(5)
+            .constraints(constraints)
+            .members(members)
+            .seed(3)
+            .build()
+            .unwrap();
+        generator
+            .set_prefix(vec!["the".to_string(), "cat".to_string()])
+            .unwrap();
+
+        for _ in 0..10 {
+            let tokens = generator.generate_tokens().unwrap();
+
+            assert_eq!(tokens[1..3], ["the", "cat"]);
+            assert!(tokens.contains(&"dog".to_string()));
+            assert!(!tokens.contains(&"mat".to_string()));
+        }
+
+        generator.set_prefix(Vec::new()).unwrap();
+        let parts = parse_template("the cat ___ on the ___ .").unwrap();
+        let tokens = generator.generate_template(&parts).unwrap();
+        assert_eq!(tokens[1..3], ["the", "cat"]);
+        assert_eq!(tokens[6], "dog");
+    }
+
+    #[test]
+    fn test_generator_sampler() {
+        for sampler in [Sampler::Uniform, Sampler::Weighted] {
+            let mut generator = Generator::from_rules(get_rules())
+                .length(6)
+                .sampler(sampler)
+                .build()
+                .unwrap();
+            generator.pin(2, "dog").unwrap();
+
+            let tokens = generator.generate_tokens().unwrap();
+
+            assert_eq!(tokens.len(), 8);
+            assert_eq!(tokens[2], "dog");
+            assert!(verify(&tokens, generator.rules()).is_empty());
+        }
+    }
+
+    #[test]
+    fn test_generator_sampler_constraints() {
+        let constraints = Constraints {
+            required: ["dog".to_string()].into_iter().collect(),
+            ..Default::default()
+        };
+        let mut generator = Generator::from_rules(get_rules())
+            .length(3)
+            .sampler(Sampler::Uniform)
+            .constraints(constraints)
+            .build()
+            .unwrap();
+
+        for _ in 0..10 {
+            let tokens = generator.generate_tokens().unwrap();
+            assert_eq!(tokens[1..4], ["the", "dog", "."]);
+        }
+    }
+
+    #[test]
+    fn test_generator_count() {
+        let mut generator = Generator::from_rules(get_rules())
+            .length(3)
+            .build()
+            .unwrap();
+        // the cat, the mat and the dog, each followed by a dot
+        assert_eq!(generator.count(), Ok(Some(3)));
+
+        generator.pin(2, "dog").unwrap();
+        assert_eq!(generator.count(), Ok(Some(1)));
+    }
+}
@@ -0,0 +1,293 @@
+use std::collections::HashMap;
+
+use super::rules::{CompiledRules, WfcVector};
+
+/// The value a set becomes when no word fits it, allowing anything next to it
+pub static PLACEHOLDER: String = String::new();
+
+/// The index of the support coming from the left neighbor
+const LEFT: usize = 0;
+/// The index of the support coming from the right neighbor
+const RIGHT: usize = 1;
+
+/// Keeps a vector arc consistent (AC-4): every word of a set is allowed by at least one word of each
+/// neighbor. Removing a word only updates the counters of the words it allowed in the neighbors.
+#[derive(Clone)]
+pub struct Propagator<'a> {
+    rules: &'a CompiledRules,
+    /// How many words of the left and right neighbors allow each word still possible in each set,
+    /// only the words still possible being kept
+    supports: Vec<HashMap<usize, [u32; 2]>>,
+    /// The sets which became contradictions, their neighbors are no longer limited by them
+    wildcards: Vec<bool>,
+    /// Removed words (set index, word id) which were not propagated to the neighbors yet
+    removed: Vec<(usize, usize)>,
+}
+
+impl<'a> Propagator<'a> {
+    /// Counts the supports of every word in the vector and propagates until it is arc consistent
+    pub fn new(wfc_vector: &mut WfcVector, rules: &'a CompiledRules) -> Self {
+        let length = wfc_vector.len();
+
+        let mut supports: Vec<HashMap<usize, [u32; 2]>> = wfc_vector
+            .iter()
+            .map(|set| {
+                set.iter()
+                    .filter_map(|word| rules.ids.get(word))
+                    .map(|id| (*id, [0, 0]))
+                    .collect()
+            })
+            .collect();
+        for index in 0..length {
+            let ids: Vec<usize> = supports[index].keys().copied().collect();
+            for id in ids {
+                if index + 1 < length {
+                    for after in &rules.after[id] {
+                        if let Some(support) = supports[index + 1].get_mut(after) {
+                            support[LEFT] += 1;
+                        }
+                    }
+                }
+                if index > 0 {
+                    for before in &rules.before[id] {
+                        if let Some(support) = supports[index - 1].get_mut(before) {
+                            support[RIGHT] += 1;
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut propagator = Self {
+            rules,
+            supports,
+            wildcards: wfc_vector
+                .iter()
+                .map(|set| set.contains(&PLACEHOLDER))
+                .collect(),
+            removed: Vec::new(),
+        };
+
+        for index in 0..length {
+            let unsupported: Vec<usize> = propagator.supports[index]
+                .keys()
+                .filter(|id| !propagator.is_supported(index, **id))
+                .copied()
+                .collect();
+            for id in unsupported {
+                propagator.remove(wfc_vector, index, id);
+            }
+        }
+
+        propagator.propagate(wfc_vector);
+        propagator
+    }
+
+    /// Returns wether the word at the given set is allowed by both of its neighbors
+    fn is_supported(&self, index: usize, id: usize) -> bool {
+        let supports = self.supports[index].get(&id).copied().unwrap_or([0, 0]);
+        let left = index == 0 || self.wildcards[index - 1] || supports[LEFT] > 0;
+        let right =
+            index + 1 == self.supports.len() || self.wildcards[index + 1] || supports[RIGHT] > 0;
+        left && right
+    }
+
+    /// Removes a word from a set, without propagating it yet
+    fn remove(&mut self, wfc_vector: &mut WfcVector, index: usize, id: usize) {
+        if self.supports[index].remove(&id).is_some() {
+            wfc_vector[index].remove(&self.rules.words[id]);
+            self.removed.push((index, id));
+        }
+    }
+
+    /// Registers the words removed from the set at the index since it was last seen (after a collapse
+    /// or after words were removed by constraints), without propagating them yet
+    pub fn sync(&mut self, wfc_vector: &mut WfcVector, index: usize) {
+        let removed: Vec<usize> = self.supports[index]
+            .keys()
+            .filter(|id| !wfc_vector[index].contains(&self.rules.words[**id]))
+            .copied()
+            .collect();
+        for id in removed {
+            self.remove(wfc_vector, index, id);
+        }
+    }
+
+    /// Returns wether the set at the index became a contradiction
+    pub fn is_wildcard(&self, index: usize) -> bool {
+        self.wildcards[index]
+    }
+
+    /// Decrements the supports of the neighbors of every removed word, removing the words left
+    /// without support until nothing changes
+    pub fn propagate(&mut self, wfc_vector: &mut WfcVector) {
+        while let Some((index, id)) = self.removed.pop() {
+            if self.wildcards[index] {
+                continue;
+            }
+            if wfc_vector[index].is_empty() {
+                wfc_vector[index].insert(PLACEHOLDER.clone());
+                self.wildcards[index] = true;
+                continue;
+            }
+
+            let rules = self.rules;
+            if index + 1 < wfc_vector.len() {
+                for after in &rules.after[id] {
+                    self.decrement(wfc_vector, index + 1, *after, LEFT);
+                }
+            }
+            if index > 0 {
+                for before in &rules.before[id] {
+                    self.decrement(wfc_vector, index - 1, *before, RIGHT);
+                }
+            }
+        }
+    }
+
+    /// Removes one support of the word from the given side, removing the word if it has none left
+    fn decrement(&mut self, wfc_vector: &mut WfcVector, index: usize, id: usize, side: usize) {
+        let support = match self.supports[index].get_mut(&id) {
+            Some(support) => &mut support[side],
+            None => return,
+        };
+        *support = support.saturating_sub(1);
+
+        if *support == 0 && !self.wildcards[index] {
+            self.remove(wfc_vector, index, id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::wfc::wfc::rules::{Allowed, CompiledRules, Rules, WfcVector, END, START};
+
+    use super::{Propagator, PLACEHOLDER};
+
+    fn get_rules() -> Rules {
+        let mut rules = HashMap::<String, Allowed>::new();
+        rules.insert(
+            START.to_string(),
+            Allowed::new(
+                [END.to_string()].into_iter().collect(),
+                ["hello".to_string()].into_iter().collect(),
+            ),
+        );
+        rules.insert(
+            "hello".to_string(),
+            Allowed::new(
+                [START.to_string()].into_iter().collect(),
+                ["world".to_string(), "there".to_string()]
+                    .into_iter()
+                    .collect(),
+            ),
+        );
+        rules.insert(
+            "world".to_string(),
+            Allowed::new(
+                ["hello".to_string()].into_iter().collect(),
+                ["!".to_string()].into_iter().collect(),
+            ),
+        );
+        rules.insert(
+            "there".to_string(),
+            Allowed::new(
+                ["hello".to_string()].into_iter().collect(),
+                [END.to_string()].into_iter().collect(),
+            ),
+        );
+        rules.insert(
+            "!".to_string(),
+            Allowed::new(
+                ["world".to_string()].into_iter().collect(),
+                [END.to_string()].into_iter().collect(),
+            ),
+        );
+        rules.insert(
+            END.to_string(),
+            Allowed::new(
+                ["there".to_string(), "!".to_string()].into_iter().collect(),
+                [START.to_string()].into_iter().collect(),
+            ),
+        );
+        rules
+    }
+
+    fn get_vector(sets: &[&[&str]]) -> WfcVector {
+        sets.iter()
+            .map(|set| set.iter().map(|s| s.to_string()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_propagator_new_sanity() {
+        let rules = get_rules();
+        let compiled = CompiledRules::new(&rules);
+        let all: Vec<&str> = rules.keys().map(String::as_str).collect();
+        let mut vector = get_vector(&[&[START], &["hello"], &all, &["!", END]]);
+
+        Propagator::new(&mut vector, &compiled);
+
+        assert_eq!(
+            vector,
+            get_vector(&[&[START], &["hello"], &["world", "there"], &["!", END]])
+        );
+    }
+
+    #[test]
+    fn test_propagator_new_single_path() {
+        let rules = get_rules();
+        let compiled = CompiledRules::new(&rules);
+        let all: Vec<&str> = rules.keys().map(String::as_str).collect();
+        let mut vector = get_vector(&[&[START], &all, &all, &all, &[END]]);
+
+        Propagator::new(&mut vector, &compiled);
+
+        assert_eq!(
+            vector,
+            get_vector(&[&[START], &["hello"], &["world"], &["!"], &[END]])
+        );
+    }
+
+    #[test]
+    fn test_propagator_sync() {
+        let rules = get_rules();
+        let compiled = CompiledRules::new(&rules);
+        let mut vector = get_vector(&[&[START], &["hello"], &["world", "there"], &["!", END]]);
+
+        let mut propagator = Propagator::new(&mut vector, &compiled);
+        vector[2].remove("there");
+        propagator.sync(&mut vector, 2);
+        propagator.propagate(&mut vector);
+
+        assert_eq!(
+            vector,
+            get_vector(&[&[START], &["hello"], &["world"], &["!"]])
+        );
+    }
+
+    #[test]
+    fn test_propagator_contradiction() {
+        let rules = get_rules();
+        let compiled = CompiledRules::new(&rules);
+        let all: Vec<&str> = rules.keys().map(String::as_str).collect();
+        let mut vector = get_vector(&[&[START], &all, &["!"], &all, &[END]]);
+
+        Propagator::new(&mut vector, &compiled);
+
+        // Nothing fits around "!", but the placeholders don't limit "!" or their other neighbors
+        assert_eq!(
+            vector,
+            get_vector(&[
+                &[START],
+                &[PLACEHOLDER.as_str()],
+                &["!"],
+                &[PLACEHOLDER.as_str()],
+                &[END]
+            ])
+        );
+    }
+}
@@ -0,0 +1,249 @@
+use std::collections::{HashMap, HashSet};
+
+use super::rules::{add_transition, Allowed, Rules, END, START};
+
+/// The word rare words are replaced with when they are mapped instead of dropped
+pub static UNKNOWN: &str = "<unk>";
+
+/// The options controlling which words and transitions are pruned from the rules
+#[derive(Clone, PartialEq, Eq)]
+pub struct PruneOptions {
+    /// Words appearing fewer times than this are dropped
+    pub min_count: usize,
+    /// Only this many of the most frequent words are kept
+    pub max_vocab: Option<usize>,
+    /// Transitions seen fewer times than this are dropped
+    pub min_transition_count: usize,
+    /// Replace dropped words with [UNKNOWN] instead of removing them
+    pub map_to_unknown: bool,
+}
+
+impl Default for PruneOptions {
+    fn default() -> Self {
+        Self {
+            min_count: 1,
+            max_vocab: None,
+            min_transition_count: 1,
+            map_to_unknown: false,
+        }
+    }
+}
+
+/// Returns wether the word is the start or the end, which are never pruned
+fn is_special(word: &str) -> bool {
+    word == START || word == END
+}
+
+/// Returns how many times a transition was seen, unweighted transitions count once
+fn get_transition_count(allowed: &Allowed, after: &str) -> usize {
+    allowed.counts.get(after).copied().unwrap_or(1)
+}
+
+/// Returns how many times each word appeared in the corpus, based on the transitions leading to it
+pub fn get_word_counts(rules: &Rules) -> HashMap<String, usize> {
+    let mut counts: HashMap<String, usize> = rules.keys().map(|word| (word.clone(), 0)).collect();
+
+    for (word, allowed) in rules {
+        for after in &allowed.after {
+            if word == END && after == START {
+                continue;
+            }
+            if let Some(count) = counts.get_mut(after) {
+                *count += get_transition_count(allowed, after);
+            }
+        }
+    }
+
+    counts
+}
+
+/// Returns the words which are kept by the minimum count and maximum vocabulary options
+fn get_vocabulary(rules: &Rules, options: &PruneOptions) -> HashSet<String> {
+    let mut counts: Vec<(String, usize)> = get_word_counts(rules)
+        .into_iter()
+        .filter(|(word, count)| !is_special(word) && *count >= options.min_count)
+        .collect();
+    counts.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then(a.cmp(b)));
+
+    if let Some(max_vocab) = options.max_vocab {
+        counts.truncate(max_vocab);
+    }
+
+    counts.into_iter().map(|(word, _)| word).collect()
+}
+
+/// Returns the words reachable from [from], going over [after] or [before] sets
+pub(crate) fn get_reachable(rules: &Rules, from: &str, forward: bool) -> HashSet<String> {
+    let mut reachable = HashSet::<String>::new();
+    let mut stack = vec![from.to_string()];
+
+    while let Some(word) = stack.pop() {
+        if !reachable.insert(word.clone()) {
+            continue;
+        }
+        if let Some(allowed) = rules.get(&word) {
+            let next = match forward {
+                true => &allowed.after,
+                false => &allowed.before,
+            };
+            stack.extend(next.iter().filter(|w| !is_special(w)).cloned());
+        }
+    }
+
+    reachable
+}
+
+/// Removes every word which doesn't lie on a path from the start to the end
+fn remove_unreachable(rules: Rules) -> Rules {
+    let from_start = get_reachable(&rules, START, true);
+    let to_end = get_reachable(&rules, END, false);
+    let is_kept =
+        |word: &String| is_special(word) || (from_start.contains(word) && to_end.contains(word));
+
+    rules
+        .into_iter()
+        .filter(|(word, _)| is_kept(word))
+        .map(|(word, mut allowed)| {
+            allowed.before.retain(|w| is_kept(w));
+            allowed.after.retain(|w| is_kept(w));
+            allowed.counts.retain(|w, _| is_kept(w));
+            (word, allowed)
+        })
+        .collect()
+}
+
+/// Returns the rules without rare words and rare transitions, keeping every remaining word
+/// reachable between the start and the end
+pub fn prune_rules(rules: &Rules, options: &PruneOptions) -> Rules {
+    let vocabulary = get_vocabulary(rules, options);
+    let map_word = |word: &String| -> Option<String> {
+        if is_special(word) || vocabulary.contains(word) {
+            Some(word.clone())
+        } else if options.map_to_unknown {
+            Some(UNKNOWN.to_string())
+        } else {
+            None
+        }
+    };
+
+    let mut counts = HashMap::<(String, String), usize>::new();
+    for (word, allowed) in rules {
+        for after in &allowed.after {
+            if word == END && after == START {
+                continue;
+            }
+            if let (Some(from), Some(to)) = (map_word(word), map_word(after)) {
+                *counts.entry((from, to)).or_insert(0) += get_transition_count(allowed, after);
+            }
+        }
+    }
+
+    let mut pruned = Rules::new();
+    pruned.insert(START.to_string(), Allowed::empty());
+    pruned.insert(END.to_string(), Allowed::empty());
+    for ((from, to), count) in counts {
+        if count >= options.min_transition_count {
+            add_transition(&mut pruned, &from, &to, count);
+        }
+    }
+    add_transition(&mut pruned, END, START, 0);
+
+    remove_unreachable(pruned)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::wfc::wfc::rules::{add_transition, Rules, END, START};
+
+    use super::{get_word_counts, prune_rules, PruneOptions, UNKNOWN};
+
+    fn get_rules() -> Rules {
+        let mut rules = Rules::new();
+        let transitions = [
+            (START, "the", 4),
+            ("the", "cat", 3),
+            ("the", "dog", 1),
+            ("cat", "sat", 2),
+            ("cat", END, 1),
+            ("dog", "sat", 1),
+            ("sat", END, 3),
+        ];
+        for (before, after, count) in transitions {
+            add_transition(&mut rules, before, after, count);
+        }
+        add_transition(&mut rules, END, START, 0);
+        rules
+    }
+
+    #[test]
+    fn test_get_word_counts_sanity() {
+        let counts = get_word_counts(&get_rules());
+
+        assert_eq!(counts["the"], 4);
+        assert_eq!(counts["cat"], 3);
+        assert_eq!(counts["dog"], 1);
+        assert_eq!(counts["sat"], 3);
+        assert_eq!(counts[END], 4);
+        assert_eq!(counts[START], 0);
+    }
+
+    #[test]
+    fn test_prune_rules_default_is_identity() {
+        let rules = get_rules();
+        let pruned = prune_rules(&rules, &PruneOptions::default());
+
+        assert_eq!(pruned.keys().len(), rules.keys().len());
+        for (word, allowed) in &rules {
+            assert_eq!(pruned[word].before, allowed.before);
+            assert_eq!(pruned[word].after, allowed.after);
+            assert_eq!(pruned[word].counts, allowed.counts);
+        }
+    }
+
+    #[test]
+    fn test_prune_rules_min_count() {
+        let options = PruneOptions {
+            min_count: 2,
+            ..Default::default()
+        };
+        let pruned = prune_rules(&get_rules(), &options);
+
+        assert_eq!(pruned.keys().len(), 5);
+        assert!(!pruned.contains_key("dog"));
+        assert!(!pruned["the"].after.contains("dog"));
+        assert!(!pruned["sat"].before.contains("dog"));
+    }
+
+    #[test]
+    fn test_prune_rules_unknown() {
+        let options = PruneOptions {
+            max_vocab: Some(1),
+            map_to_unknown: true,
+            ..Default::default()
+        };
+        let pruned = prune_rules(&get_rules(), &options);
+
+        assert_eq!(pruned.keys().len(), 4);
+        assert!(pruned.contains_key(UNKNOWN));
+        assert!(!pruned.contains_key("cat"));
+        assert_eq!(pruned["the"].counts[UNKNOWN], 4);
+        assert_eq!(pruned[UNKNOWN].counts[UNKNOWN], 3);
+        assert_eq!(pruned[UNKNOWN].counts[END], 4);
+    }
+
+    #[test]
+    fn test_prune_rules_keeps_reachable() {
+        let options = PruneOptions {
+            min_transition_count: 2,
+            ..Default::default()
+        };
+        let pruned = prune_rules(&get_rules(), &options);
+
+        // "dog" is only reachable through rare transitions, and "cat" can only end through "sat"
+        assert_eq!(pruned.keys().len(), 5);
+        assert!(!pruned.contains_key("dog"));
+        assert!(!pruned["cat"].after.contains(END));
+        assert!(pruned[END].after.contains(START));
+        assert!(pruned[START].before.contains(END));
+    }
+}
@@ -1,5 +1,5 @@
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 
 /// A type representing the rules by which the algorithm works (A map of word to its allowed words)
 pub type Rules = HashMap<String, Allowed>;
@@ -13,30 +13,116 @@ pub static START: &str = "\x02";
 pub static END: &str = "\x03";
 
 /// This struct holds the set of words which are allowed to appear after and before a given word
-#[derive(Clone, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Allowed {
     pub before: HashSet<String>,
     pub after: HashSet<String>,
+    /// How many times each word of [after] followed this word, empty when the rules aren't weighted
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub counts: BTreeMap<String, usize>,
 }
 
 impl Allowed {
-    /// Creates a new allowed struct
+    /// Creates a new unweighted allowed struct
     pub const fn new(before: HashSet<String>, after: HashSet<String>) -> Self {
-        Self { before, after }
+        Self {
+            before,
+            after,
+            counts: BTreeMap::new(),
+        }
     }
 
     /// Creates a new empty allowed struct
     pub fn empty() -> Self {
+        Self::new(HashSet::<String>::new(), HashSet::<String>::new())
+    }
+}
+
+/// The rules with every word replaced by its index, for fast propagation
+pub struct CompiledRules {
+    /// The words, sorted
+    pub words: Vec<String>,
+    /// The index of every word
+    pub ids: HashMap<String, usize>,
+    /// The indexes of the words allowed after every word
+    pub after: Vec<Vec<usize>>,
+    /// The indexes of the words allowed before every word
+    pub before: Vec<Vec<usize>>,
+}
+
+impl CompiledRules {
+    /// Compiles the rules, ignoring words which are allowed but have no rules of their own
+    pub fn new(rules: &Rules) -> Self {
+        let mut words: Vec<String> = rules.keys().cloned().collect();
+        words.sort();
+        let ids: HashMap<String, usize> = words
+            .iter()
+            .enumerate()
+            .map(|(i, word)| (word.clone(), i))
+            .collect();
+
+        let to_ids = |set: &HashSet<String>| {
+            let mut result: Vec<usize> = set
+                .iter()
+                .filter_map(|word| ids.get(word))
+                .copied()
+                .collect();
+            result.sort();
+            result
+        };
+        let after = words
+            .iter()
+            .map(|word| to_ids(&rules[word].after))
+            .collect();
+        let before = words
+            .iter()
+            .map(|word| to_ids(&rules[word].before))
+            .collect();
+
         Self {
-            before: HashSet::<String>::new(),
-            after: HashSet::<String>::new(),
+            words,
+            ids,
+            after,
+            before,
+        }
+    }
+}
+
+/// Allows [after] to appear after [before], adding [count] to the times it was seen (0 for unweighted rules)
+pub fn add_transition(rules: &mut Rules, before: &str, after: &str, count: usize) {
+    let allowed = rules
+        .entry(before.to_string())
+        .or_insert_with(Allowed::empty);
+    allowed.after.insert(after.to_string());
+    if count > 0 {
+        *allowed.counts.entry(after.to_string()).or_insert(0) += count;
+    }
+
+    let allowed = rules
+        .entry(after.to_string())
+        .or_insert_with(Allowed::empty);
+    allowed.before.insert(before.to_string());
+}
+
+/// Merges two rules learned from different parts of a corpus, allowing every transition of both
+/// and adding up the counts. Merging is associative and commutative, so the parts can be merged in
+/// any order.
+pub fn merge_rules(mut rules: Rules, other: Rules) -> Rules {
+    for (word, allowed) in other {
+        let merged = rules.entry(word).or_insert_with(Allowed::empty);
+        merged.before.extend(allowed.before);
+        merged.after.extend(allowed.after);
+        for (after, count) in allowed.counts {
+            *merged.counts.entry(after).or_insert(0) += count;
         }
     }
+    rules
 }
 
+/// Creates the vector for generating [size] tokens, with every word possible between the start and end
 pub fn generate_wfc_vector(rules: &Rules, size: usize) -> WfcVector {
     let mut vector = WfcVector::with_capacity(size + 2);
-    let set: HashSet<String> = rules.keys().into_iter().cloned().collect();
+    let set: HashSet<String> = rules.keys().cloned().collect();
 
     for _i in 0..(size + 2) {
         vector.push(set.clone())
@@ -52,7 +138,9 @@ pub fn generate_wfc_vector(rules: &Rules, size: usize) -> WfcVector {
 mod tests {
     use std::collections::HashMap;
 
-    use super::{generate_wfc_vector, Allowed, Rules, END, START};
+    use super::{
+        add_transition, generate_wfc_vector, merge_rules, Allowed, CompiledRules, Rules, END, START,
+    };
 
     fn get_rules() -> Rules {
         let mut rules = HashMap::<String, Allowed>::new();
@@ -120,4 +208,60 @@ mod tests {
         assert_eq!(vector[size + 1].iter().next().unwrap(), &END.to_string());
         assert_eq!(vector[1].len(), rules.keys().len());
     }
+
+    #[test]
+    fn test_add_transition_sanity() {
+        let mut rules = Rules::new();
+        add_transition(&mut rules, "hello", "world", 2);
+        add_transition(&mut rules, "hello", "world", 1);
+        add_transition(&mut rules, "hello", "there", 0);
+
+        assert_eq!(rules.keys().len(), 3);
+        assert!(rules["hello"].after.contains("world"));
+        assert!(rules["hello"].after.contains("there"));
+        assert!(rules["world"].before.contains("hello"));
+        assert!(rules["there"].before.contains("hello"));
+        assert_eq!(rules["hello"].counts["world"], 3);
+        assert!(!rules["hello"].counts.contains_key("there"));
+    }
+
+    #[test]
+    fn test_merge_rules_sanity() {
+        let mut first = Rules::new();
+        add_transition(&mut first, "hello", "world", 2);
+        let mut second = Rules::new();
+        add_transition(&mut second, "hello", "world", 1);
+        add_transition(&mut second, "hello", "there", 1);
+        let mut third = Rules::new();
+        add_transition(&mut third, "world", "there", 4);
+
+        let left = merge_rules(merge_rules(first.clone(), second.clone()), third.clone());
+        let right = merge_rules(first, merge_rules(second, third));
+
+        assert_eq!(left, right);
+        assert_eq!(left.keys().len(), 3);
+        assert_eq!(left["hello"].counts["world"], 3);
+        assert_eq!(left["hello"].counts["there"], 1);
+        assert!(left["there"].before.contains("hello"));
+        assert!(left["there"].before.contains("world"));
+    }
+
+    #[test]
+    fn test_compiled_rules_sanity() {
+        let rules = get_rules();
+        let compiled = CompiledRules::new(&rules);
+
+        assert_eq!(compiled.words.len(), rules.keys().len());
+        assert_eq!(compiled.words[compiled.ids["hello"]], "hello");
+
+        let after: Vec<&String> = compiled.after[compiled.ids["hello"]]
+            .iter()
+            .map(|id| &compiled.words[*id])
+            .collect();
+        assert_eq!(after, vec!["there", "world"]);
+        assert_eq!(
+            compiled.before[compiled.ids["hello"]],
+            vec![compiled.ids[START]]
+        );
+    }
 }
@@ -0,0 +1,272 @@
+use rand::seq::SliceRandom;
+use rand::Rng;
+use std::collections::HashSet;
+use std::str::FromStr;
+
+use super::rules::{CompiledRules, Rules, WfcVector};
+
+/// How the words of a text are chosen
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Sampler {
+    /// Collapsing the sets one at a time, see [iterate_with](super::algorithm::iterate_with)
+    #[default]
+    Wfc,
+    /// Every text fitting the rules equally likely, only approximately beyond 2^53 texts as the
+    /// numbers of texts are floats. With classes, every sequence of classes is equally likely,
+    /// not every text once the classes are replaced with their words.
+    Uniform,
+    /// Every text fitting the rules as likely as its transitions were seen in the corpus
+    Weighted,
+}
+
+impl FromStr for Sampler {
+    type Err = &'static str;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "wfc" => Ok(Self::Wfc),
+            "uniform" => Ok(Self::Uniform),
+            "weighted" => Ok(Self::Weighted),
+            _ => Err("Unknown sampler"),
+        }
+    }
+}
+
+/// Returns which words of the compiled rules are in the set
+fn get_ids(set: &HashSet<String>, compiled_rules: &CompiledRules) -> Vec<bool> {
+    let mut ids = vec![false; compiled_rules.words.len()];
+    for word in set {
+        if let Some(id) = compiled_rules.ids.get(word) {
+            ids[*id] = true;
+        }
+    }
+    ids
+}
+
+/// Returns the weight of every transition of the compiled rules: the probability of the next word
+/// when weighted (or for words without counts), else 1
+fn get_weights(rules: &Rules, compiled_rules: &CompiledRules, weighted: bool) -> Vec<Vec<f64>> {
+    compiled_rules
+        .words
+        .iter()
+        .zip(&compiled_rules.after)
+        .map(|(word, after)| {
+            let counts = &rules[word].counts;
+            let get_count = |id: &usize| counts.get(&compiled_rules.words[*id]).copied();
+            let total: usize = after.iter().filter_map(get_count).sum();
+            after
+                .iter()
+                .map(|id| match (weighted, total) {
+                    (true, total) if total > 0 => get_count(id).unwrap_or(0) as f64 / total as f64,
+                    _ => 1.0,
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Returns how many texts fit the vector and the rules, or None if there are more than fit in a
+/// u128
+pub fn count_texts(wfc_vector: &WfcVector, compiled_rules: &CompiledRules) -> Option<u128> {
+    let sets: Vec<Vec<bool>> = wfc_vector
+        .iter()
+        .map(|set| get_ids(set, compiled_rules))
+        .collect();
+    let last = sets.last()?;
+
+    let mut ways: Vec<u128> = last.iter().map(|alive| *alive as u128).collect();
+    for alive in sets.iter().rev().skip(1) {
+        let mut previous = vec![0u128; ways.len()];
+        for (id, count) in previous.iter_mut().enumerate().filter(|(id, _)| alive[*id]) {
+            for after in &compiled_rules.after[id] {
+                *count = count.checked_add(ways[*after])?;
+            }
+        }
+        ways = previous;
+    }
+
+    ways.into_iter()
+        .try_fold(0u128, |total, count| total.checked_add(count))
+}
+
+/// Returns how many texts fit the vector and the rules
+pub fn count(wfc_vector: &WfcVector, rules: &Rules) -> Result<u128, &'static str> {
+    count_texts(wfc_vector, &CompiledRules::new(rules)).ok_or("There are too many texts to count")
+}
+
+/// Draws a text from every text fitting the vector and the rules, with the rules already compiled.
+/// Every text is equally likely, or as likely as the product of the probabilities of its
+/// transitions when [weighted]. The weights are floats, so with more than 2^53 texts the draw is
+/// only approximately uniform.
+pub fn sample_with(
+    wfc_vector: &WfcVector,
+    rules: &Rules,
+    compiled_rules: &CompiledRules,
+    weighted: bool,
+    rng: &mut impl Rng,
+) -> Result<Vec<String>, &'static str> {
+    let sets: Vec<Vec<bool>> = wfc_vector
+        .iter()
+        .map(|set| get_ids(set, compiled_rules))
+        .collect();
+    if sets.iter().any(|alive| !alive.contains(&true)) {
+        return Err("No text of this length fits the rules");
+    }
+    let weights = get_weights(rules, compiled_rules, weighted);
+
+    // The total weight of the rest of the texts after every word of every set, scaled so that
+    // long texts don't underflow
+    let mut rest: Vec<Vec<f64>> = vec![Vec::new(); sets.len()];
+    rest[sets.len() - 1] = sets[sets.len() - 1]
+        .iter()
+        .map(|alive| *alive as u8 as f64)
+        .collect();
+    for index in (0..sets.len() - 1).rev() {
+        let mut weight: Vec<f64> = vec![0.0; compiled_rules.words.len()];
+        for (id, total) in weight.iter_mut().enumerate() {
+            if sets[index][id] {
+                *total = compiled_rules.after[id]
+                    .iter()
+                    .zip(&weights[id])
+                    .map(|(after, weight)| weight * rest[index + 1][*after])
+                    .sum();
+            }
+        }
+        let max = weight.iter().copied().fold(0.0, f64::max);
+        if max > 0.0 {
+            weight.iter_mut().for_each(|total| *total /= max);
+        }
+        rest[index] = weight;
+    }
+
+    let mut candidates: Vec<(usize, f64)> = rest[0]
+        .iter()
+        .copied()
+        .enumerate()
+        .filter(|(_, weight)| *weight > 0.0)
+        .collect();
+    let mut result = Vec::<String>::with_capacity(sets.len());
+    for index in 0..sets.len() {
+        let (id, _) = *candidates
+            .choose_weighted(rng, |(_, weight)| *weight)
+            .map_err(|_| "No text of this length fits the rules")?;
+        result.push(compiled_rules.words[id].clone());
+
+        if let Some(next) = rest.get(index + 1) {
+            candidates = compiled_rules.after[id]
+                .iter()
+                .zip(&weights[id])
+                .map(|(after, weight)| (*after, weight * next[*after]))
+                .filter(|(_, weight)| *weight > 0.0)
+                .collect();
+        }
+    }
+
+    Ok(result)
+}
+
+/// Draws a text from every text fitting the vector and the rules, like [sample_with]
+pub fn sample(
+    wfc_vector: &WfcVector,
+    rules: &Rules,
+    weighted: bool,
+    rng: &mut impl Rng,
+) -> Result<Vec<String>, &'static str> {
+    sample_with(wfc_vector, rules, &CompiledRules::new(rules), weighted, rng)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use std::collections::HashMap;
+
+    use crate::wfc::parsing::text_parse::generate_rules;
+    use crate::wfc::wfc::algorithm::verify;
+    use crate::wfc::wfc::rules::{add_transition, generate_wfc_vector, Rules, END, START};
+
+    use super::{count, sample};
+
+    /// The start followed by a or b, then c or d (b being seen three times as often), then the end
+    fn get_rules() -> Rules {
+        let mut rules = Rules::new();
+        let transitions = [
+            (START, "a", 1),
+            (START, "b", 3),
+            ("a", "c", 1),
+            ("b", "c", 1),
+            ("b", "d", 1),
+            ("c", END, 1),
+            ("d", END, 1),
+        ];
+        for (before, after, count) in transitions {
+            add_transition(&mut rules, before, after, count);
+        }
+        add_transition(&mut rules, END, START, 0);
+        rules
+    }
+
+    #[test]
+    fn test_count_sanity() {
+        let rules = get_rules();
+
+        assert_eq!(count(&generate_wfc_vector(&rules, 2), &rules), Ok(3));
+        assert_eq!(count(&generate_wfc_vector(&rules, 3), &rules), Ok(0));
+        // a text, the end and the start, then another text
+        assert_eq!(count(&generate_wfc_vector(&rules, 6), &rules), Ok(9));
+    }
+
+    #[test]
+    fn test_count_overflow() {
+        let rules = generate_rules("a a b a b b".to_string());
+
+        assert!(count(&generate_wfc_vector(&rules, 60), &rules).is_ok());
+        assert_eq!(
+            count(&generate_wfc_vector(&rules, 200), &rules),
+            Err("There are too many texts to count")
+        );
+    }
+
+    #[test]
+    fn test_sample_sanity() {
+        let rules = get_rules();
+        let vector = generate_wfc_vector(&rules, 2);
+        let mut rng = StdRng::seed_from_u64(7);
+
+        for weighted in [false, true] {
+            let mut counts = HashMap::<Vec<String>, usize>::new();
+            for _ in 0..3000 {
+                let text = sample(&vector, &rules, weighted, &mut rng).unwrap();
+                assert!(verify(&text, &rules).is_empty());
+                *counts.entry(text[1..3].to_vec()).or_insert(0) += 1;
+            }
+
+            let get = |a: &str, b: &str| counts[&vec![a.to_string(), b.to_string()]] as f64;
+            assert_eq!(counts.len(), 3);
+            match weighted {
+                // 1/3 each
+                false => assert!((get("a", "c") / 3000.0 - 1.0 / 3.0).abs() < 0.05),
+                // 1/4 for a c, 3/8 for b c and b d
+                true => {
+                    assert!((get("a", "c") / 3000.0 - 0.25).abs() < 0.05);
+                    assert!((get("b", "d") / 3000.0 - 0.375).abs() < 0.05);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_sample_impossible() {
+        let rules = get_rules();
+
+        assert_eq!(
+            sample(
+                &generate_wfc_vector(&rules, 3),
+                &rules,
+                false,
+                &mut StdRng::seed_from_u64(0)
+            ),
+            Err("No text of this length fits the rules")
+        );
+    }
+}
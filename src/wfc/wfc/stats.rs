@@ -0,0 +1,183 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use super::rules::{Allowed, Rules, END, START};
+
+/// How connected a single word is
+#[derive(Serialize)]
+pub struct WordDegree {
+    pub word: String,
+    pub before: usize,
+    pub after: usize,
+}
+
+/// A summary of the rules, for inspecting compiled rules files
+#[derive(Serialize)]
+pub struct RulesStats {
+    /// How many words have rules
+    pub vocabulary_size: usize,
+    /// How many (word, next word) pairs are allowed, not counting the end going back to the start
+    pub transitions: usize,
+    pub average_after: f64,
+    pub median_after: f64,
+    pub average_before: f64,
+    pub median_before: f64,
+    /// The words with the most words allowed before and after them
+    pub most_connected: Vec<WordDegree>,
+    /// Words which can't be followed by any word with rules
+    pub dead_ends: Vec<String>,
+    /// The entropy (in bits) of the next word of every word, only for weighted rules
+    pub entropy: Option<BTreeMap<String, f64>>,
+}
+
+/// Returns the average and the median of the values
+fn get_average_and_median(mut values: Vec<usize>) -> (f64, f64) {
+    if values.is_empty() {
+        return (0.0, 0.0);
+    }
+    values.sort();
+
+    let average = values.iter().sum::<usize>() as f64 / values.len() as f64;
+    let middle = values.len() / 2;
+    let median = match values.len() % 2 {
+        0 => (values[middle - 1] + values[middle]) as f64 / 2.0,
+        _ => values[middle] as f64,
+    };
+
+    (average, median)
+}
+
+/// Returns the entropy of the next word, in bits
+fn get_entropy(allowed: &Allowed) -> f64 {
+    let total = allowed.counts.values().sum::<usize>() as f64;
+    allowed
+        .counts
+        .values()
+        .filter(|count| **count > 0)
+        .map(|count| {
+            let probability = *count as f64 / total;
+            probability * (1.0 / probability).log2()
+        })
+        .sum()
+}
+
+/// Returns statistics about the rules, listing the [top] most connected words
+pub fn get_rules_stats(rules: &Rules, top: usize) -> RulesStats {
+    let (average_after, median_after) =
+        get_average_and_median(rules.values().map(|allowed| allowed.after.len()).collect());
+    let (average_before, median_before) =
+        get_average_and_median(rules.values().map(|allowed| allowed.before.len()).collect());
+
+    let mut most_connected: Vec<WordDegree> = rules
+        .iter()
+        .map(|(word, allowed)| WordDegree {
+            word: word.clone(),
+            before: allowed.before.len(),
+            after: allowed.after.len(),
+        })
+        .collect();
+    most_connected.sort_by(|a, b| {
+        (b.before + b.after)
+            .cmp(&(a.before + a.after))
+            .then(a.word.cmp(&b.word))
+    });
+    most_connected.truncate(top);
+
+    let mut dead_ends: Vec<String> = rules
+        .iter()
+        .filter(|(_, allowed)| !allowed.after.iter().any(|word| rules.contains_key(word)))
+        .map(|(word, _)| word.clone())
+        .collect();
+    dead_ends.sort();
+
+    let is_weighted = rules.values().any(|allowed| !allowed.counts.is_empty());
+    let entropy = is_weighted.then(|| {
+        rules
+            .iter()
+            .filter(|(_, allowed)| !allowed.counts.is_empty())
+            .map(|(word, allowed)| (word.clone(), get_entropy(allowed)))
+            .collect()
+    });
+
+    let restarts = rules
+        .get(END)
+        .is_some_and(|allowed| allowed.after.contains(START));
+    let transitions = rules
+        .values()
+        .map(|allowed| allowed.after.len())
+        .sum::<usize>()
+        - restarts as usize;
+
+    RulesStats {
+        vocabulary_size: rules.len(),
+        transitions,
+        average_after,
+        median_after,
+        average_before,
+        median_before,
+        most_connected,
+        dead_ends,
+        entropy,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::wfc::wfc::rules::{add_transition, Rules, END, START};
+
+    use super::{get_average_and_median, get_rules_stats};
+
+    fn get_rules() -> Rules {
+        let mut rules = Rules::new();
+        let transitions = [
+            (START, "the", 4),
+            ("the", "cat", 2),
+            ("the", "dog", 2),
+            ("cat", END, 2),
+            ("dog", END, 1),
+            ("dog", "barked", 1),
+        ];
+        for (before, after, count) in transitions {
+            add_transition(&mut rules, before, after, count);
+        }
+        add_transition(&mut rules, END, START, 0);
+        rules
+    }
+
+    #[test]
+    fn test_get_average_and_median_sanity() {
+        assert_eq!(get_average_and_median(vec![3, 1, 2]), (2.0, 2.0));
+        assert_eq!(get_average_and_median(vec![4, 1, 2, 1]), (2.0, 1.5));
+        assert_eq!(get_average_and_median(vec![]), (0.0, 0.0));
+    }
+
+    #[test]
+    fn test_get_rules_stats_sanity() {
+        let stats = get_rules_stats(&get_rules(), 2);
+
+        assert_eq!(stats.vocabulary_size, 6);
+        assert_eq!(stats.transitions, 6);
+        assert_eq!(stats.median_after, 1.0);
+        assert_eq!(stats.most_connected.len(), 2);
+        assert_eq!(stats.most_connected[0].word, END);
+        assert_eq!(stats.most_connected[0].before, 2);
+        assert_eq!(stats.most_connected[0].after, 1);
+        assert_eq!(stats.most_connected[1].word, "dog");
+        assert_eq!(stats.dead_ends, vec!["barked".to_string()]);
+
+        let entropy = stats.entropy.unwrap();
+        assert_eq!(entropy["the"], 1.0);
+        assert_eq!(entropy[START], 0.0);
+        assert!(!entropy.contains_key(END));
+    }
+
+    #[test]
+    fn test_get_rules_stats_unweighted() {
+        let mut rules = get_rules();
+        for allowed in rules.values_mut() {
+            allowed.counts.clear();
+        }
+
+        assert!(get_rules_stats(&rules, 10).entropy.is_none());
+    }
+}